@@ -0,0 +1,11 @@
+// Writes the TTL of the LV2 bundle into the directory given as the only argument.
+fn main() -> std::io::Result<()> {
+    let dir = std::env::args()
+        .nth(1)
+        .expect("usage: lv2_bundle <bundle directory>");
+
+    jimtel::lv2::write_bundle::<loudness_ceiling::LoudnessCeiling>(
+        std::path::Path::new(&dir),
+        concat!("jimtel_", env!("CARGO_PKG_NAME"), ".so"),
+    )
+}
@@ -1,47 +1,77 @@
 mod params;
 
+use std::ops::Range;
 use std::sync::Arc;
 
-use vst::buffer::AudioBuffer;
+use vst::buffer::{AudioBuffer, Inputs, Outputs};
+use vst::channels::ChannelInfo;
 use vst::editor::Editor as VstEditor;
 use vst::plugin::{Category, HostCallback, Info, Plugin, PluginParameters};
 
+use jimtel::channels::{CHANNELS_NUM, DECLARED_CHANNELS_NUM};
 use jimtel::editor::Editor;
 use jimtel::params::Params;
-use params::LoudnessCeilingParams;
+use params::{LoudnessCeilingParams, LoudnessCeilingParamsSmoothers};
+
+pub struct LoudnessCeiling {
+    sample_rate_hz: f32,
 
-struct LoudnessCeiling {
     loudness: jimtel::loudness::Loudness,
     params: Arc<LoudnessCeilingParams>,
+    smoothers: LoudnessCeilingParamsSmoothers,
 
     envelope: jimtel::envelope::Envelope,
     max_loundess: f32,
     coefficient: f32,
     previous_reset: bool,
+
+    true_peak: jimtel::true_peak::TruePeak,
+    max_true_peak: f32,
 }
 
 impl Plugin for LoudnessCeiling {
     fn new(_host: HostCallback) -> Self {
         let sample_rate_hz = 48000.0;
-        let samples_num_per_window = (sample_rate_hz * 3.0) as usize;
+        let params = Arc::new(LoudnessCeilingParams::new());
+        let smoothers = params.smoothers(sample_rate_hz);
 
         Self {
-            loudness: jimtel::loudness::Loudness::new(sample_rate_hz, samples_num_per_window, 1),
-            params: Arc::new(LoudnessCeilingParams::new()),
+            sample_rate_hz,
+
+            loudness: jimtel::loudness::Loudness::new(
+                sample_rate_hz,
+                CHANNELS_NUM,
+                samples_num_per_window(sample_rate_hz),
+                1,
+            ),
+            params,
+            smoothers,
 
             envelope: jimtel::envelope::Envelope::new(sample_rate_hz),
             max_loundess: 0.0,
             coefficient: 1.0,
             previous_reset: false,
+
+            true_peak: jimtel::true_peak::TruePeak::new(CHANNELS_NUM),
+            max_true_peak: 0.0,
         }
     }
 
+    fn set_sample_rate(&mut self, rate: f32) {
+        self.sample_rate_hz = rate;
+        self.reset();
+    }
+
+    fn resume(&mut self) {
+        self.reset();
+    }
+
     fn get_info(&self) -> Info {
         Info {
             name: "Jimtel Loudness Ceiling".to_string(),
             unique_id: 291815611,
-            inputs: 2,
-            outputs: 2,
+            inputs: DECLARED_CHANNELS_NUM as i32,
+            outputs: DECLARED_CHANNELS_NUM as i32,
             parameters: LoudnessCeilingParams::num_params() as i32,
             category: Category::Mastering,
             preset_chunks: true,
@@ -50,14 +80,53 @@ impl Plugin for LoudnessCeiling {
         }
     }
 
+    fn get_input_info(&self, input: i32) -> ChannelInfo {
+        jimtel::channels::channel_info(input)
+    }
+
+    fn get_output_info(&self, output: i32) -> ChannelInfo {
+        jimtel::channels::channel_info(output)
+    }
+
     fn process(&mut self, buffer: &mut AudioBuffer<f32>) {
-        let (input_buffer, output_buffer) = buffer.split();
-        let (in_left_buffer, in_right_buffer) = input_buffer.split_at(1);
-        let (mut out_left_buffer, output_buffer) = output_buffer.split_at_mut(1);
-        let (mut out_right_buffer, _output_buffer) = output_buffer.split_at_mut(1);
+        let samples_num = buffer.samples();
+        let (inputs, mut outputs) = buffer.split();
+        let channels_num = inputs.len().min(outputs.len()).min(CHANNELS_NUM);
+
+        // Params are re-read every sub-block so automation is followed within the
+        // host's block.
+        for samples in
+            jimtel::sub_blocks::sub_blocks(samples_num, jimtel::sub_blocks::SUB_BLOCK_SAMPLES_NUM)
+        {
+            self.process_sub_block(&inputs, &mut outputs, channels_num, samples);
+        }
+    }
+
+    fn get_parameter_object(&mut self) -> Arc<dyn PluginParameters> {
+        self.params.clone()
+    }
+
+    fn get_editor(&mut self) -> Option<Box<dyn VstEditor>> {
+        Some(Box::new(Editor::new(
+            "Jimtel Loudness Ceiling".to_string(),
+            1024.0,
+            600.0,
+            self.params.clone(),
+        )))
+    }
+}
+
+impl LoudnessCeiling {
+    fn process_sub_block(
+        &mut self,
+        inputs: &Inputs<f32>,
+        outputs: &mut Outputs<f32>,
+        channels_num: usize,
+        samples: Range<usize>,
+    ) {
+        // The gains ramp toward their params per sample.
+        self.smoothers.set_targets(&self.params);
 
-        let input_gain = self.params.input_gain.get();
-        let output_gain = self.params.output_gain.get();
         let limit = self.params.limit.get();
         let hard_limit = self.params.hard_limit.get();
         let attack_ms = self.params.attack.get();
@@ -67,20 +136,33 @@ impl Plugin for LoudnessCeiling {
 
         if reset != self.previous_reset {
             self.max_loundess = limit;
+            self.true_peak.reset();
+            self.max_true_peak = 0.0;
             self.previous_reset = reset;
         }
 
         self.coefficient = limit / self.max_loundess;
 
-        for (in_left, in_right, out_left, out_right) in itertools::izip!(
-            in_left_buffer.get(0),
-            in_right_buffer.get(0),
-            out_left_buffer.get_mut(0),
-            out_right_buffer.get_mut(0),
-        ) {
-            let (loudness, _) = self
-                .loudness
-                .add_samples(in_left * input_gain, in_right * input_gain);
+        // One sample per channel; channels the host does not provide stay silent.
+        let mut in_samples = [0.0; CHANNELS_NUM];
+        let mut gained_in_samples = [0.0; CHANNELS_NUM];
+        let mut out_samples = [0.0; CHANNELS_NUM];
+
+        for n in samples {
+            let input_gain = self.smoothers.input_gain.next_value();
+            let output_gain = self.smoothers.output_gain.next_value();
+
+            for (channel, (in_sample, gained_in_sample)) in in_samples
+                .iter_mut()
+                .zip(gained_in_samples.iter_mut())
+                .take(channels_num)
+                .enumerate()
+            {
+                *in_sample = inputs.get(channel)[n];
+                *gained_in_sample = *in_sample * input_gain;
+            }
+
+            let (loudness, _) = self.loudness.add_samples(&gained_in_samples);
             let loudness = self.envelope.calculate(loudness);
 
             if loudness > self.max_loundess {
@@ -95,23 +177,61 @@ impl Plugin for LoudnessCeiling {
 
             let gain = input_gain * output_gain * self.coefficient;
 
-            *out_left = (in_left * gain).min(hard_limit).max(-hard_limit);
-            *out_right = (in_right * gain).min(hard_limit).max(-hard_limit);
+            for (out_sample, in_sample) in out_samples.iter_mut().zip(&in_samples) {
+                *out_sample = (in_sample * gain).min(hard_limit).max(-hard_limit);
+            }
+
+            for (channel, out_sample) in out_samples.iter().take(channels_num).enumerate() {
+                outputs.get_mut(channel)[n] = *out_sample;
+            }
+
+            self.max_true_peak = self
+                .max_true_peak
+                .max(self.true_peak.add_samples(&out_samples));
         }
+
+        self.params
+            .max_true_peak
+            .set(self.max_true_peak.max(f32::EPSILON));
     }
 
-    fn get_parameter_object(&mut self) -> Arc<dyn PluginParameters> {
-        self.params.clone()
+    // Rebuilds all the state derived from the sample rate. The envelope coefficients
+    // are re-applied from the params on the next block.
+    fn reset(&mut self) {
+        let sample_rate_hz = self.sample_rate_hz;
+
+        self.smoothers = self.params.smoothers(sample_rate_hz);
+
+        self.loudness = jimtel::loudness::Loudness::new(
+            sample_rate_hz,
+            CHANNELS_NUM,
+            samples_num_per_window(sample_rate_hz),
+            1,
+        );
+        self.envelope = jimtel::envelope::Envelope::new(sample_rate_hz);
     }
+}
 
-    fn get_editor(&mut self) -> Option<Box<dyn VstEditor>> {
-        Some(Box::new(Editor::new(
-            "Jimtel Loudness Ceiling".to_string(),
-            1024.0,
-            360.0,
-            self.params.clone(),
-        )))
+// The ceiling follows a 3 s (short-term) loudness window.
+fn samples_num_per_window(sample_rate_hz: f32) -> usize {
+    (sample_rate_hz * 3.0) as usize
+}
+
+impl jimtel::wrapped::WrappedPlugin for LoudnessCeiling {
+    type Params = LoudnessCeilingParams;
+
+    fn params(&self) -> Arc<LoudnessCeilingParams> {
+        self.params.clone()
     }
 }
 
 vst::plugin_main!(LoudnessCeiling);
+
+#[cfg(feature = "vst3")]
+jimtel::vst3_main!(LoudnessCeiling);
+
+#[cfg(feature = "clap")]
+jimtel::clap_main!(LoudnessCeiling);
+
+#[cfg(feature = "lv2")]
+jimtel::lv2_main!(LoudnessCeiling);
@@ -4,34 +4,31 @@ use params_derive::Params;
 
 #[derive(Params)]
 pub struct LoudnessCeilingParams {
-    #[param(kind = "dB", min = "-80", max = "80")]
+    #[param(section = "Gain")]
+    #[param(kind = "dB", min = "-80", max = "80", default = "0", smooth = "20ms")]
     pub input_gain: AtomicFloat,
 
-    #[param(kind = "dB", min = "-80", max = "80")]
+    #[param(section = "Gain")]
+    #[param(kind = "dB", min = "-80", max = "80", default = "0", smooth = "20ms")]
     pub output_gain: AtomicFloat,
 
-    #[param(kind = "LKFS", min = "-80", max = "0")]
+    #[param(section = "Limiter")]
+    #[param(kind = "LKFS", min = "-80", max = "0", default = "0")]
     pub limit: AtomicFloat,
 
-    #[param(kind = "dBFS", min = "-80", max = "0")]
+    #[param(section = "Limiter")]
+    #[param(kind = "dBFS", min = "-80", max = "0", default = "0")]
     pub hard_limit: AtomicFloat,
 
-    #[param(kind = "ms", min = "0", max = "5000")]
+    #[param(section = "Limiter")]
+    #[param(kind = "ms", min = "0", max = "5000", default = "1000")]
     pub attack: AtomicFloat,
 
-    #[param(kind = "button", min = "0", max = "1")]
+    #[param(section = "Measurements")]
+    #[param(kind = "button", min = "0", max = "1", default = "0")]
     pub reset: AtomicFloat,
-}
 
-impl LoudnessCeilingParams {
-    pub fn new() -> Self {
-        Self {
-            input_gain: AtomicFloat::new(0.0),
-            output_gain: AtomicFloat::new(0.0),
-            limit: AtomicFloat::new(0.0),
-            hard_limit: AtomicFloat::new(0.0),
-            attack: AtomicFloat::new(1000.0),
-            reset: AtomicFloat::new(0.0),
-        }
-    }
+    // Highest inter-sample peak of the output since the last reset.
+    #[param(kind = "dBTP", min = "-60", max = "6", meter)]
+    pub max_true_peak: AtomicFloat,
 }
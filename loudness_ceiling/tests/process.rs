@@ -0,0 +1,93 @@
+use jimtel::test_host::{self, peak, TestHost};
+use loudness_ceiling::LoudnessCeiling;
+
+const SAMPLE_RATE_HZ: f32 = 48000.0;
+
+fn sine(dbfs: f32, seconds: f32) -> Vec<Vec<f32>> {
+    test_host::sine(SAMPLE_RATE_HZ, dbfs, seconds)
+}
+
+fn host() -> TestHost<LoudnessCeiling> {
+    TestHost::new(SAMPLE_RATE_HZ, 2)
+}
+
+#[test]
+fn silence_stays_silent() {
+    let mut host = host();
+    let outputs = host.process_in_blocks(&vec![vec![0.0; 48000]; 2], 512);
+
+    assert!(outputs.iter().flatten().all(|&sample| sample == 0.0));
+}
+
+#[test]
+fn input_under_the_limit_passes_unchanged() {
+    let mut host = host();
+    let inputs = sine(-6.0, 2.0);
+    let outputs = host.process_in_blocks(&inputs, 512);
+
+    assert_eq!(outputs, inputs);
+
+    let max_true_peak_dbtp = host.meter("max_true_peak");
+    assert!(
+        (max_true_peak_dbtp - (-6.0)).abs() < 0.1,
+        "{} dBTP",
+        max_true_peak_dbtp
+    );
+}
+
+#[test]
+fn loudness_over_the_limit_is_held_under_it() {
+    let mut host = host();
+    host.set_parameter_text("limit", "-20");
+    host.set_parameter_text("attack", "100");
+    let outputs = host.process_in_blocks(&sine(-6.0, 8.0), 512);
+
+    // The last 2 s, once the 3 s loudness window and the attack have settled.
+    let tail: Vec<Vec<f32>> = outputs
+        .iter()
+        .map(|output| output[288000..].to_vec())
+        .collect();
+    let lkfs = jimtel::normalize::measure(SAMPLE_RATE_HZ, &tail).integrated_lkfs;
+    assert!(lkfs <= -20.0 + 0.1, "{} LKFS", lkfs);
+}
+
+#[test]
+fn hard_limit_clips_the_output() {
+    let mut host = host();
+    host.set_parameter_text("hard_limit", "-12");
+    let outputs = host.process_in_blocks(&sine(-6.0, 1.0), 512);
+
+    let hard_limit = 10f32.powf(-12.0 / 20.0);
+    assert!(
+        (peak(&outputs) - hard_limit).abs() < 1e-6,
+        "{}",
+        peak(&outputs)
+    );
+}
+
+#[test]
+fn reset_restarts_the_true_peak_meter() {
+    let mut host = host();
+    host.process_in_blocks(&sine(-6.0, 1.0), 512);
+    assert!(host.meter("max_true_peak") > -7.0);
+
+    // The button toggles its value; any change resets.
+    host.set_parameter("reset", 1.0);
+    host.process_in_blocks(&vec![vec![0.0; 4800]; 2], 512);
+    assert!(host.meter("max_true_peak") < -60.0);
+}
+
+#[test]
+fn block_size_does_not_change_the_output() {
+    let inputs = sine(-6.0, 1.0);
+    let run = |block_size| {
+        let mut host = host();
+        host.set_parameter_text("limit", "-20");
+        host.process_in_blocks(&inputs, block_size)
+    };
+    let expected = run(512);
+
+    for block_size in [1, 31, 32, 100, 4096] {
+        assert_eq!(run(block_size), expected, "block size {}", block_size);
+    }
+}
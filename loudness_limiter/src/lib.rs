@@ -1,46 +1,105 @@
 mod params;
 
+use std::ops::Range;
 use std::sync::Arc;
 
-use vst::buffer::AudioBuffer;
+use vst::buffer::{AudioBuffer, Inputs, Outputs};
+use vst::channels::ChannelInfo;
 use vst::editor::Editor as VstEditor;
 use vst::plugin::{Category, HostCallback, Info, Plugin, PluginParameters};
 
+use jimtel::channels::{CHANNELS_NUM, DECLARED_CHANNELS_NUM};
 use jimtel::editor::Editor;
-use jimtel::params::Params;
-use params::LoudnessLimiterParams;
+use jimtel::params::{Choice, Params};
+use params::{BeyondPowerLimit, LoudnessLimiterParams, LoudnessLimiterParamsSmoothers};
 
-struct LoudnessLimiter {
+const TRUE_PEAK_LOOKAHEAD_MS: f32 = 1.5;
+
+pub struct LoudnessLimiter {
     sample_rate_hz: f32,
+    latency_reporter: Arc<jimtel::latency::LatencyReporter>,
 
     loudness: jimtel::loudness::Loudness,
     output_loudness: jimtel::loudness::Loudness,
     params: Arc<LoudnessLimiterParams>,
+    smoothers: LoudnessLimiterParamsSmoothers,
 
     power_envelope: jimtel::envelope::Envelope,
     loudness_power_envelope: jimtel::envelope::Envelope,
 
     delay_buffer: jimtel::delay_buffer::DelayBuffer,
+
+    brickwall: jimtel::brickwall::Brickwall,
+    previous_true_peak_limit: bool,
+
+    integrated_loudness: jimtel::loudness::IntegratedLoudness,
+    loudness_range: jimtel::loudness::LoudnessRange,
+    true_peak: jimtel::true_peak::TruePeak,
+    max_true_peak: f32,
+    previous_reset_measurements: bool,
 }
 
 impl Plugin for LoudnessLimiter {
-    fn new(_host: HostCallback) -> Self {
+    fn new(host: HostCallback) -> Self {
         let sample_rate_hz = 48000.0;
+        let params = Arc::new(LoudnessLimiterParams::new());
+        let smoothers = params.smoothers(sample_rate_hz);
 
         Self {
             sample_rate_hz,
+            latency_reporter: Arc::new(jimtel::latency::LatencyReporter::new(host, 0)),
 
-            loudness: jimtel::loudness::Loudness::new(sample_rate_hz, 1, 1),
-            output_loudness: jimtel::loudness::Loudness::new(sample_rate_hz, 1, 1),
-            params: Arc::new(LoudnessLimiterParams::new()),
+            loudness: jimtel::loudness::Loudness::new(sample_rate_hz, CHANNELS_NUM, 1, 1),
+            output_loudness: jimtel::loudness::Loudness::new(sample_rate_hz, CHANNELS_NUM, 1, 1),
+            params,
+            smoothers,
 
             power_envelope: jimtel::envelope::Envelope::new(sample_rate_hz),
             loudness_power_envelope: jimtel::envelope::Envelope::new(sample_rate_hz),
 
-            delay_buffer: jimtel::delay_buffer::DelayBuffer::new(0),
+            delay_buffer: jimtel::delay_buffer::DelayBuffer::new(CHANNELS_NUM, 0),
+
+            brickwall: jimtel::brickwall::Brickwall::new(
+                sample_rate_hz,
+                CHANNELS_NUM,
+                TRUE_PEAK_LOOKAHEAD_MS,
+            ),
+            previous_true_peak_limit: false,
+
+            integrated_loudness: jimtel::loudness::IntegratedLoudness::new(
+                sample_rate_hz,
+                CHANNELS_NUM,
+            ),
+            loudness_range: jimtel::loudness::LoudnessRange::new(sample_rate_hz, CHANNELS_NUM),
+            true_peak: jimtel::true_peak::TruePeak::new(CHANNELS_NUM),
+            max_true_peak: 0.0,
+            previous_reset_measurements: false,
         }
     }
 
+    fn set_sample_rate(&mut self, rate: f32) {
+        // The measurements carry on across resumes, such as the transport stopping
+        // and starting, but not into another sample rate.
+        if rate != self.sample_rate_hz {
+            self.sample_rate_hz = rate;
+            self.integrated_loudness =
+                jimtel::loudness::IntegratedLoudness::new(rate, CHANNELS_NUM);
+            self.loudness_range = jimtel::loudness::LoudnessRange::new(rate, CHANNELS_NUM);
+            self.true_peak.reset();
+            self.max_true_peak = 0.0;
+        }
+
+        self.reset();
+    }
+
+    fn resume(&mut self) {
+        self.reset();
+
+        // Hosts resume on the UI thread, so a pending latency change can go out now.
+        self.latency_reporter.set(self.latency());
+        self.latency_reporter.report();
+    }
+
     fn get_info(&self) -> Info {
         // The dev build gets a distinct name and unique_id so a DAW treats it as
         // a separate plugin and it can coexist with the production build.
@@ -53,26 +112,70 @@ impl Plugin for LoudnessLimiter {
         Info {
             name,
             unique_id,
-            inputs: 2,
-            outputs: 2,
+            inputs: DECLARED_CHANNELS_NUM as i32,
+            outputs: DECLARED_CHANNELS_NUM as i32,
             parameters: LoudnessLimiterParams::num_params() as i32,
             category: Category::Mastering,
             preset_chunks: true,
+            initial_delay: self.latency(),
 
             ..Default::default()
         }
     }
 
+    fn get_input_info(&self, input: i32) -> ChannelInfo {
+        jimtel::channels::channel_info(input)
+    }
+
+    fn get_output_info(&self, output: i32) -> ChannelInfo {
+        jimtel::channels::channel_info(output)
+    }
+
     fn process(&mut self, buffer: &mut AudioBuffer<f32>) {
-        let (input_buffer, output_buffer) = buffer.split();
-        let (in_left_buffer, in_right_buffer) = input_buffer.split_at(1);
-        let (mut out_left_buffer, output_buffer) = output_buffer.split_at_mut(1);
-        let (mut out_right_buffer, _output_buffer) = output_buffer.split_at_mut(1);
+        let samples_num = buffer.samples();
+        let (inputs, mut outputs) = buffer.split();
+        let channels_num = inputs.len().min(outputs.len()).min(CHANNELS_NUM);
+
+        // Params are re-read every sub-block so automation is followed within the
+        // host's block.
+        for samples in
+            jimtel::sub_blocks::sub_blocks(samples_num, jimtel::sub_blocks::SUB_BLOCK_SAMPLES_NUM)
+        {
+            self.process_sub_block(&inputs, &mut outputs, channels_num, samples);
+        }
+    }
+
+    fn get_parameter_object(&mut self) -> Arc<dyn PluginParameters> {
+        self.params.clone()
+    }
+
+    fn get_editor(&mut self) -> Option<Box<dyn VstEditor>> {
+        let title = if cfg!(feature = "dev") {
+            "Jimtel Loudness Limiter (dev)".to_string()
+        } else {
+            "Jimtel Loudness Limiter".to_string()
+        };
 
-        let input_gain = self.params.input_gain.get();
-        let output_gain = self.params.output_gain.get();
+        let latency_reporter = self.latency_reporter.clone();
+
+        Some(Box::new(
+            Editor::new(title, 1280.0, 1080.0, self.params.clone())
+                .on_idle(move || latency_reporter.report()),
+        ))
+    }
+}
+
+impl LoudnessLimiter {
+    fn process_sub_block(
+        &mut self,
+        inputs: &Inputs<f32>,
+        outputs: &mut Outputs<f32>,
+        channels_num: usize,
+        samples: Range<usize>,
+    ) {
+        // Gains and the loudness target ramp toward their params per sample.
+        self.smoothers.set_targets(&self.params);
 
-        let base_loudness_power = self.params.loudness.get();
         let samples_num_per_loudness_window =
             (self.params.loudness_window.get() / 1000.0 * self.sample_rate_hz) as usize;
         let loudness_attack_ms = self.params.loudness_attack.get();
@@ -84,9 +187,12 @@ impl Plugin for LoudnessLimiter {
             (self.params.power_window.get() / 1000.0 * self.sample_rate_hz) as usize;
         let power_release_ms = self.params.power_release.get();
 
-        let silence_beyond_power_limit = self.params.silence_beyond_power.get();
+        let beyond_power_limit =
+            BeyondPowerLimit::from_value(self.params.silence_beyond_power.get());
+
+        let delay_samples = self.delay_samples();
 
-        let delay_samples = (self.params.delay.get() / 1000.0 * self.sample_rate_hz) as usize;
+        let true_peak_limit = self.params.true_peak_limit.get() > 0.5;
 
         self.loudness.set_samples_num_per_windows(
             samples_num_per_loudness_window,
@@ -101,25 +207,60 @@ impl Plugin for LoudnessLimiter {
 
         self.delay_buffer.set_delay(delay_samples);
 
-        // Meter readouts, captured from the last sample of the block. Loudness is
+        // Start the brickwall afresh whenever it is switched on, so its lookahead
+        // does not replay audio from the last time it was in use.
+        if true_peak_limit && !self.previous_true_peak_limit {
+            self.brickwall.reset();
+        }
+        self.previous_true_peak_limit = true_peak_limit;
+
+        // Keep the host's delay compensation in step with the delay and brickwall.
+        // The host hears of a change from the UI thread.
+        self.latency_reporter.set(self.latency());
+
+        self.brickwall
+            .set_ceiling(self.params.true_peak_ceiling.get());
+        self.brickwall
+            .set_release(self.params.true_peak_release.get());
+
+        // The reset button toggles its value; any change restarts the measurements.
+        let reset_measurements = self.params.reset_measurements.get() < 0.5;
+        if reset_measurements != self.previous_reset_measurements {
+            self.integrated_loudness.reset();
+            self.loudness_range.reset();
+            self.true_peak.reset();
+            self.max_true_peak = 0.0;
+            self.previous_reset_measurements = reset_measurements;
+        }
+
+        // Meter readouts, captured from the last sample of the sub-block. Loudness is
         // stored as mean power (the LKFS meter kind takes the log); gain reduction
         // is stored as an amplitude coefficient (the dB meter kind takes the log).
-        let input_gain_power = input_gain * input_gain;
-        let output_gain_power = output_gain * output_gain;
-
         let mut meter_input_loudness_power = f32::EPSILON;
         let mut meter_output_loudness_power = f32::EPSILON;
         let mut meter_reduction = 1.0;
 
-        for (in_left, in_right, out_left, out_right) in itertools::izip!(
-            in_left_buffer.get(0),
-            in_right_buffer.get(0),
-            out_left_buffer.get_mut(0),
-            out_right_buffer.get_mut(0),
-        ) {
-            let (loudness_power, power) = self
-                .loudness
-                .add_samples(in_left * input_gain, in_right * input_gain);
+        // One sample per channel; channels the host does not provide stay silent.
+        let mut in_samples = [0.0; CHANNELS_NUM];
+        let mut gained_in_samples = [0.0; CHANNELS_NUM];
+        let mut out_samples = [0.0; CHANNELS_NUM];
+
+        for n in samples {
+            let input_gain = self.smoothers.input_gain.next_value();
+            let output_gain = self.smoothers.output_gain.next_value();
+            let base_loudness_power = self.smoothers.loudness.next_value();
+
+            for (channel, (in_sample, gained_in_sample)) in in_samples
+                .iter_mut()
+                .zip(gained_in_samples.iter_mut())
+                .take(channels_num)
+                .enumerate()
+            {
+                *in_sample = inputs.get(channel)[n];
+                *gained_in_sample = *in_sample * input_gain;
+            }
+
+            let (loudness_power, power) = self.loudness.add_samples(&gained_in_samples);
 
             let enveloped_power = self.power_envelope.calculate(power);
             let enveloped_loudness_power = self.loudness_power_envelope.calculate(loudness_power);
@@ -127,30 +268,50 @@ impl Plugin for LoudnessLimiter {
 
             let loudness_coefficient = (base_loudness_power / enveloped_loudness_power).min(1.0);
 
-            let power_limit_coefficient =
-                if silence_beyond_power_limit > 0.5 && enveloped_power > base_power {
-                    0.0
-                } else {
-                    (base_power / enveloped_power).min(1.0)
-                };
+            let power_limit_coefficient = if beyond_power_limit == BeyondPowerLimit::Silence
+                && enveloped_power > base_power
+            {
+                0.0
+            } else {
+                (base_power / enveloped_power).min(1.0)
+            };
 
             let gain =
                 input_gain * output_gain * (loudness_coefficient * power_limit_coefficient).sqrt();
 
-            let (delayed_in_left, delayed_in_right) = self.delay_buffer.add(*in_left, *in_right);
-            *out_left = delayed_in_left * gain;
-            *out_right = delayed_in_right * gain;
+            let delayed_in_samples = self.delay_buffer.add(&in_samples);
+            for (out_sample, delayed_in_sample) in out_samples.iter_mut().zip(delayed_in_samples) {
+                *out_sample = delayed_in_sample * gain;
+            }
 
-            let (output_loudness_power, _) =
-                self.output_loudness.add_samples(*out_left, *out_right);
+            if true_peak_limit {
+                self.brickwall.add_samples(&mut out_samples);
+            }
+
+            for (channel, out_sample) in out_samples.iter().take(channels_num).enumerate() {
+                outputs.get_mut(channel)[n] = *out_sample;
+            }
+
+            let (output_loudness_power, _) = self.output_loudness.add_samples(&out_samples);
+            self.integrated_loudness.add_samples(&out_samples);
+            self.loudness_range.add_samples(&out_samples);
+            self.max_true_peak = self
+                .max_true_peak
+                .max(self.true_peak.add_samples(&out_samples));
 
             meter_input_loudness_power = loudness_power.max(f32::EPSILON);
             meter_output_loudness_power = output_loudness_power.max(f32::EPSILON);
             meter_reduction = (loudness_coefficient * power_limit_coefficient).sqrt();
         }
 
-        // Divide out the user gains to recover the pre-gain loudness (exact, since
-        // each gain is a constant scalar applied uniformly across the window).
+        // Divide out the user gains to recover the pre-gain loudness (exact once
+        // the gains have settled, since each is then a constant scalar applied
+        // uniformly across the window).
+        let input_gain = self.smoothers.input_gain.current();
+        let output_gain = self.smoothers.output_gain.current();
+        let input_gain_power = input_gain * input_gain;
+        let output_gain_power = output_gain * output_gain;
+
         self.params
             .input_loudness_post_gain
             .set(meter_input_loudness_power);
@@ -166,26 +327,71 @@ impl Plugin for LoudnessLimiter {
         self.params
             .gain_reduction
             .set(meter_reduction.max(f32::EPSILON));
+        self.params
+            .integrated_loudness
+            .set(self.integrated_loudness.power().max(f32::EPSILON));
+        self.params.loudness_range.set(self.loudness_range.range());
+        self.params
+            .max_true_peak
+            .set(self.max_true_peak.max(f32::EPSILON));
     }
 
-    fn get_parameter_object(&mut self) -> Arc<dyn PluginParameters> {
-        self.params.clone()
+    fn delay_samples(&self) -> usize {
+        (self.params.delay.get() / 1000.0 * self.sample_rate_hz) as usize
     }
 
-    fn get_editor(&mut self) -> Option<Box<dyn VstEditor>> {
-        let title = if cfg!(feature = "dev") {
-            "Jimtel Loudness Limiter (dev)".to_string()
+    // The output lags the input by the delay, plus the lookahead of the true-peak
+    // brickwall while it is enabled.
+    fn latency(&self) -> i32 {
+        let brickwall_latency = if self.params.true_peak_limit.get() > 0.5 {
+            self.brickwall.latency()
         } else {
-            "Jimtel Loudness Limiter".to_string()
+            0
         };
 
-        Some(Box::new(Editor::new(
-            title,
-            1280.0,
-            1080.0,
-            self.params.clone(),
-        )))
+        (self.delay_samples() + brickwall_latency) as i32
+    }
+
+    // Rebuilds the processing state derived from the sample rate, leaving the
+    // measurements alone. Window lengths, envelope coefficients and the delay are
+    // re-applied from the params on the next block.
+    fn reset(&mut self) {
+        let sample_rate_hz = self.sample_rate_hz;
+
+        self.smoothers = self.params.smoothers(sample_rate_hz);
+
+        self.loudness = jimtel::loudness::Loudness::new(sample_rate_hz, CHANNELS_NUM, 1, 1);
+        self.output_loudness = jimtel::loudness::Loudness::new(sample_rate_hz, CHANNELS_NUM, 1, 1);
+
+        self.power_envelope = jimtel::envelope::Envelope::new(sample_rate_hz);
+        self.loudness_power_envelope = jimtel::envelope::Envelope::new(sample_rate_hz);
+
+        self.delay_buffer = jimtel::delay_buffer::DelayBuffer::new(CHANNELS_NUM, 0);
+
+        self.brickwall =
+            jimtel::brickwall::Brickwall::new(sample_rate_hz, CHANNELS_NUM, TRUE_PEAK_LOOKAHEAD_MS);
+    }
+}
+
+impl jimtel::wrapped::WrappedPlugin for LoudnessLimiter {
+    type Params = LoudnessLimiterParams;
+
+    fn params(&self) -> Arc<LoudnessLimiterParams> {
+        self.params.clone()
+    }
+
+    fn latency_samples(&self) -> u32 {
+        self.latency() as u32
     }
 }
 
 vst::plugin_main!(LoudnessLimiter);
+
+#[cfg(feature = "vst3")]
+jimtel::vst3_main!(LoudnessLimiter);
+
+#[cfg(feature = "clap")]
+jimtel::clap_main!(LoudnessLimiter);
+
+#[cfg(feature = "lv2")]
+jimtel::lv2_main!(LoudnessLimiter);
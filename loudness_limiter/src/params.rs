@@ -1,43 +1,97 @@
 use vst::util::AtomicFloat;
 
+use jimtel::params::Choice;
 use params_derive::Params;
 
+// What happens to the signal while its power is beyond the limit derived from the
+// loudness.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BeyondPowerLimit {
+    Limit,
+    Silence,
+}
+
+impl Choice for BeyondPowerLimit {
+    const NAMES: &'static [&'static str] = &["limit", "silence"];
+
+    fn from_index(index: usize) -> Self {
+        match index {
+            1 => BeyondPowerLimit::Silence,
+            _ => BeyondPowerLimit::Limit,
+        }
+    }
+}
+
 #[derive(Params)]
 pub struct LoudnessLimiterParams {
-    #[param(kind = "dB", min = "-80", max = "80")]
+    // The dev build starts with a hotter input and a lower loudness target.
+    #[param(section = "Gain")]
+    #[param(kind = "dB", min = "-80", max = "80", smooth = "20ms")]
+    #[cfg_attr(feature = "dev", param(default = "20"))]
+    #[cfg_attr(not(feature = "dev"), param(default = "0"))]
     pub input_gain: AtomicFloat,
 
-    #[param(kind = "dB", min = "-80", max = "80")]
+    #[param(section = "Gain")]
+    #[param(kind = "dB", min = "-80", max = "80", default = "0", smooth = "20ms")]
     pub output_gain: AtomicFloat,
 
-    #[param(kind = "LKFS", min = "-80", max = "0")]
+    #[param(section = "Loudness detector")]
+    #[param(kind = "LKFS", min = "-80", max = "0", smooth = "20ms")]
+    #[cfg_attr(feature = "dev", param(default = "-28"))]
+    #[cfg_attr(not(feature = "dev"), param(default = "-23"))]
     pub loudness: AtomicFloat,
 
-    #[param(kind = "ms", min = "1", max = "1000")]
+    #[param(section = "Loudness detector")]
+    #[param(kind = "ms", min = "1", max = "1000", default = "1000", curve = "log")]
     pub loudness_window: AtomicFloat,
 
-    #[param(kind = "ms", min = "0", max = "1000")]
+    #[param(section = "Loudness detector")]
+    #[param(kind = "ms", min = "0", max = "1000", default = "50")]
     pub loudness_attack: AtomicFloat,
 
-    #[param(kind = "ms", min = "0", max = "1000")]
+    #[param(section = "Loudness detector")]
+    #[param(kind = "ms", min = "0", max = "1000", default = "0")]
     pub loudness_release: AtomicFloat,
 
-    #[param(kind = "dB", min = "0", max = "80")]
+    #[param(section = "Power limiter")]
+    #[param(kind = "dB", min = "0", max = "80", default = "5")]
     pub power_from_loudness: AtomicFloat,
 
-    #[param(kind = "ms", min = "1", max = "32")]
+    #[param(section = "Power limiter")]
+    #[param(kind = "ms", min = "1", max = "32", default = "6", precision = "2")]
     pub power_window: AtomicFloat,
 
-    #[param(kind = "ms", min = "0", max = "10000")]
+    #[param(section = "Power limiter")]
+    #[param(kind = "ms", min = "0", max = "10000", default = "10000", skew = "0.3")]
     pub power_release: AtomicFloat,
 
-    #[param(kind = "checkbox", min = "0", max = "1")]
+    // Was a checkbox; its 0/1 values still select the same variants.
+    #[param(section = "Power limiter")]
+    #[param(kind = "choice", choices = "BeyondPowerLimit", default = "0")]
     pub silence_beyond_power: AtomicFloat,
 
-    #[param(kind = "ms", min = "0", max = "1000")]
+    #[param(section = "Delay")]
+    #[param(kind = "ms", min = "0", max = "1000", default = "0")]
     pub delay: AtomicFloat,
 
+    #[param(section = "Measurements")]
+    #[param(kind = "button", min = "0", max = "1", default = "0")]
+    pub reset_measurements: AtomicFloat,
+
+    #[param(section = "True peak limiter")]
+    #[param(kind = "checkbox", min = "0", max = "1", default = "0")]
+    pub true_peak_limit: AtomicFloat,
+
+    #[param(section = "True peak limiter")]
+    #[param(kind = "dBTP", min = "-20", max = "0", default = "-1")]
+    pub true_peak_ceiling: AtomicFloat,
+
+    #[param(section = "True peak limiter")]
+    #[param(kind = "ms", min = "1", max = "1000", default = "100")]
+    pub true_peak_release: AtomicFloat,
+
     // min/max on a meter are its plot's display range (Y axis), not a slider range.
+    // Meters start at min unless given a default.
     // The pre/post-gain pair shares a group so the editor shows one selectable line.
     #[param(kind = "LKFS", min = "-60", max = "12", meter, group = "input_loudness")]
     pub input_loudness_pre_gain: AtomicFloat,
@@ -51,65 +105,119 @@ pub struct LoudnessLimiterParams {
     #[param(kind = "LKFS", min = "-60", max = "12", meter, group = "output_loudness")]
     pub output_loudness_post_gain: AtomicFloat,
 
-    #[param(kind = "dB", min = "-60", max = "1", meter)]
+    #[param(kind = "dB", min = "-60", max = "1", default = "0", meter)]
     pub gain_reduction: AtomicFloat,
-}
 
-impl LoudnessLimiterParams {
-    pub fn new() -> Self {
-        // The dev build starts with a hotter input and a lower loudness target.
-        let (default_input_gain, default_loudness) = if cfg!(feature = "dev") {
-            (
-                10f32.powf(20.0 / 20.0),           // 20dB
-                10f32.powf((-28.0 + 0.691) / 10.0), // -28LKFS
-            )
-        } else {
-            (
-                1.0,                                // 0dB
-                10f32.powf((-23.0 + 0.691) / 10.0), // -23LKFS
-            )
-        };
-        let default_power_from_loudness = 10f32.powf(5.0 / 20.0); // 5dB
-
-        Self {
-            input_gain: AtomicFloat::new(default_input_gain),
-            output_gain: AtomicFloat::new(1.0), // 0dB
-            loudness: AtomicFloat::new(default_loudness),
-            loudness_window: AtomicFloat::new(1000.0),
-            loudness_attack: AtomicFloat::new(50.0),
-            loudness_release: AtomicFloat::new(0.0),
-            power_from_loudness: AtomicFloat::new(default_power_from_loudness),
-            power_window: AtomicFloat::new(6.0),
-            power_release: AtomicFloat::new(10000.0),
-            silence_beyond_power: AtomicFloat::new(0.0),
-            delay: AtomicFloat::new(0.0),
-
-            input_loudness_pre_gain: AtomicFloat::new(f32::EPSILON),
-            input_loudness_post_gain: AtomicFloat::new(f32::EPSILON),
-            output_loudness_pre_gain: AtomicFloat::new(f32::EPSILON),
-            output_loudness_post_gain: AtomicFloat::new(f32::EPSILON),
-            gain_reduction: AtomicFloat::new(1.0), // 0dB
-        }
-    }
+    // Gated (BS.1770-4) loudness of the output since the last reset.
+    #[param(kind = "LKFS", min = "-60", max = "12", meter)]
+    pub integrated_loudness: AtomicFloat,
+
+    // Loudness range (EBU Tech 3342) of the output since the last reset.
+    #[param(kind = "LU", min = "0", max = "30", meter)]
+    pub loudness_range: AtomicFloat,
+
+    // Highest inter-sample peak of the output since the last reset.
+    #[param(kind = "dBTP", min = "-60", max = "6", meter)]
+    pub max_true_peak: AtomicFloat,
 }
 
 #[cfg(test)]
 mod tests {
-    use super::LoudnessLimiterParams;
-    use jimtel::params::Params;
+    use super::{BeyondPowerLimit, LoudnessLimiterParams};
+    use jimtel::params::{Choice, Params};
+    use std::collections::BTreeMap;
     use vst::plugin::PluginParameters;
 
     #[test]
     fn meters_are_excluded_from_the_vst_parameter_set() {
-        assert_eq!(LoudnessLimiterParams::num_params(), 11);
-        assert_eq!(LoudnessLimiterParams::index_range(), 0..11);
-        assert_eq!(LoudnessLimiterParams::num_meters(), 5);
-        assert_eq!(LoudnessLimiterParams::meter_index_range(), 0..5);
+        assert_eq!(LoudnessLimiterParams::num_params(), 15);
+        assert_eq!(LoudnessLimiterParams::index_range(), 0..15);
+        assert_eq!(LoudnessLimiterParams::num_meters(), 8);
+        assert_eq!(LoudnessLimiterParams::meter_index_range(), 0..8);
+
+        // Bank data must serialize the 15 parameters only, never the meters.
+        let params = LoudnessLimiterParams::new();
+        let (_, bank): (u32, BTreeMap<String, f32>) =
+            rmp_serde::from_read_ref(&params.get_bank_data()).unwrap();
+        assert_eq!(bank.len(), 15);
+        assert!(!bank.contains_key("gain_reduction"));
+    }
+
+    #[test]
+    fn params_are_grouped_into_sections() {
+        let params = LoudnessLimiterParams::new();
+        let sections: Vec<String> = LoudnessLimiterParams::index_range()
+            .map(|index| params.get_section(index))
+            .collect();
+
+        assert_eq!(sections[0], "Gain");
+        assert_eq!(sections[2], "Loudness detector");
+        assert_eq!(sections[6], "Power limiter");
+        assert_eq!(sections[10], "Delay");
+        assert!(sections.iter().all(|section| !section.is_empty()));
+
+        // Each section is declared in one run, so the editor keeps the declared order.
+        let mut runs = sections;
+        runs.dedup();
+        let mut distinct = runs.clone();
+        distinct.sort();
+        distinct.dedup();
+        assert_eq!(runs.len(), distinct.len());
+    }
+
+    #[test]
+    fn defaults_read_back_in_display_units() {
+        let params = LoudnessLimiterParams::new();
+
+        let index = 13; // true_peak_ceiling
+        assert_eq!(params.get_name(index), "true_peak_ceiling");
+        assert_eq!(params.get_default(index), -1.0);
+        assert!((params.get_value(index) - (-1.0)).abs() < 1e-4);
 
-        // Bank data must serialize the 11 parameters only, never the meters.
+        let index = 3; // loudness_window
+        assert_eq!(params.get_default(index), 1000.0);
+        assert_eq!(params.get_value(index), 1000.0);
+
+        // Meters without a default start at the bottom of their range.
+        assert!((params.get_meter_value(5) - (-60.0)).abs() < 1e-3);
+        assert!(params.get_meter_value(4).abs() < 1e-4);
+    }
+
+    #[test]
+    fn shaped_params_give_the_low_end_more_room() {
         let params = LoudnessLimiterParams::new();
-        let bank: Vec<f32> = rmp_serde::from_read_ref(&params.get_bank_data()).unwrap();
-        assert_eq!(bank.len(), 11);
+
+        // loudness_window is logarithmic over 1..1000 ms: 10 ms sits at a third.
+        params.set_parameter(3, 1.0 / 3.0);
+        assert!((params.get_value(3) - 10.0).abs() < 1e-2);
+        assert!((params.get_parameter(3) - 1.0 / 3.0).abs() < 1e-5);
+
+        // power_release is skewed: 100 ms of 10000 ms sits at 0.01^0.3.
+        params.set_value(8, 100.0);
+        assert!((params.get_parameter(8) - 0.01f32.powf(0.3)).abs() < 1e-5);
+        params.set_parameter(8, 0.5);
+        assert!((params.get_value(8) - 10000.0 * 0.5f32.powf(1.0 / 0.3)).abs() < 1e-1);
+    }
+
+    #[test]
+    fn choice_param_reads_back_as_its_variant_name() {
+        let params = LoudnessLimiterParams::new();
+
+        let index = 9; // silence_beyond_power
+        assert_eq!(params.get_choices(index), vec!["limit", "silence"]);
+        assert_eq!(params.get_parameter_text(index), "limit");
+
+        // Two choices step at 0 and 1 of the host range.
+        params.set_parameter(index, 0.7);
+        assert_eq!(params.get_parameter(index), 1.0);
+        assert_eq!(params.get_parameter_text(index), "silence");
+        assert_eq!(
+            BeyondPowerLimit::from_value(params.silence_beyond_power.get()),
+            BeyondPowerLimit::Silence
+        );
+
+        assert!(params.string_to_parameter(index, "Limit".to_string()));
+        assert_eq!(params.get_value(index), 0.0);
     }
 
     #[test]
@@ -124,7 +232,7 @@ mod tests {
         assert_eq!(params.get_meter_name(index), "input_loudness_post_gain");
         assert_eq!(params.get_meter_unit(index), "LKFS");
         assert!((params.get_meter_value(index) - (-23.0)).abs() < 1e-3);
-        assert_eq!(params.get_meter_value_text(index), "-23");
+        assert_eq!(params.get_meter_value_text(index), "-23.0 LKFS");
     }
 
     #[test]
@@ -139,4 +247,29 @@ mod tests {
         assert_eq!(params.get_meter_unit(index), "dB");
         assert!((params.get_meter_value(index) - (-6.0206)).abs() < 1e-3);
     }
+
+    #[test]
+    fn loudness_range_meter_reads_back_in_lu() {
+        let params = LoudnessLimiterParams::new();
+
+        params.loudness_range.set(7.5);
+
+        let index = 6; // loudness_range (seventh meter)
+        assert_eq!(params.get_meter_name(index), "loudness_range");
+        assert_eq!(params.get_meter_unit(index), "LU");
+        assert_eq!(params.get_meter_value(index), 7.5);
+    }
+
+    #[test]
+    fn max_true_peak_meter_reads_back_in_dbtp() {
+        let params = LoudnessLimiterParams::new();
+
+        // A 2.0 amplitude peak is +6.02 dBTP.
+        params.max_true_peak.set(2.0);
+
+        let index = 7; // max_true_peak (eighth meter)
+        assert_eq!(params.get_meter_name(index), "max_true_peak");
+        assert_eq!(params.get_meter_unit(index), "dBTP");
+        assert!((params.get_meter_value(index) - 6.0206).abs() < 1e-3);
+    }
 }
@@ -0,0 +1,127 @@
+use std::collections::HashSet;
+
+use vst::plugin::{HostCallback, Plugin};
+
+use jimtel::lv2::plugin_ttl;
+use jimtel::params::Params;
+use jimtel::wrapped::WrappedPlugin;
+use loudness_limiter::LoudnessLimiter;
+
+// The audio ports and the latency port come before the params.
+const FIRST_PARAM_PORT: usize = 5;
+
+// The properties of each port in `ttl`, in the order they are declared.
+fn ports(ttl: &str) -> Vec<Vec<&str>> {
+    ttl.split("[\n        ")
+        .skip(1)
+        .map(|port| {
+            let port = &port[..port.find("\n    ]").unwrap()];
+            port.split(" ;\n        ").collect()
+        })
+        .collect()
+}
+
+// The value of the property named `name` of `port`, e.g. "-80.0" for
+// "lv2:minimum".
+fn property<'a>(port: &[&'a str], name: &str) -> Option<&'a str> {
+    port.iter()
+        .find_map(|property| property.strip_prefix(name)?.strip_prefix(' '))
+}
+
+fn number(port: &[&str], name: &str) -> f32 {
+    property(port, name)
+        .unwrap_or_else(|| panic!("no {} in {:?}", name, port))
+        .parse()
+        .unwrap()
+}
+
+fn quoted(text: &str) -> String {
+    format!("\"{}\"", text)
+}
+
+#[test]
+fn ports_match_the_params() {
+    let ttl = plugin_ttl::<LoudnessLimiter>();
+    let ports = ports(&ttl);
+    let params = LoudnessLimiter::new(HostCallback::default()).params();
+    type P = <LoudnessLimiter as WrappedPlugin>::Params;
+
+    assert_eq!(
+        ports.len(),
+        FIRST_PARAM_PORT + P::num_params() + P::num_meters()
+    );
+
+    for (index, port) in ports.iter().enumerate() {
+        assert_eq!(
+            property(port, "lv2:index"),
+            Some(index.to_string().as_str())
+        );
+    }
+
+    for (port, symbol) in ports
+        .iter()
+        .zip(["in_l", "in_r", "out_l", "out_r", "latency"])
+    {
+        assert_eq!(property(port, "lv2:symbol"), Some(quoted(symbol).as_str()));
+    }
+    assert!(ports[4].contains(&"lv2:designation lv2:latency"));
+
+    for index in P::index_range() {
+        let port = &ports[FIRST_PARAM_PORT + index as usize];
+        let range = params.get_range(index);
+
+        assert_eq!(port[0], "a lv2:ControlPort, lv2:InputPort");
+        assert_eq!(
+            property(port, "lv2:symbol"),
+            Some(quoted(&params.get_name(index)).as_str())
+        );
+        assert_eq!(number(port, "lv2:minimum"), *range.start());
+        assert_eq!(number(port, "lv2:maximum"), *range.end());
+        assert_eq!(number(port, "lv2:default"), params.get_default(index));
+
+        let choices = params.get_choices(index);
+        if !choices.is_empty() {
+            let scale_points = property(port, "lv2:scalePoint").unwrap();
+            for (value, choice) in choices.iter().enumerate() {
+                assert!(scale_points.contains(&format!(
+                    "[ rdfs:label {} ; rdf:value {} ]",
+                    quoted(choice),
+                    value
+                )));
+            }
+        }
+    }
+
+    for index in P::meter_index_range() {
+        let port = &ports[FIRST_PARAM_PORT + P::num_params() + index as usize];
+        let range = params.get_meter_range(index);
+
+        assert_eq!(port[0], "a lv2:ControlPort, lv2:OutputPort");
+        assert_eq!(
+            property(port, "lv2:symbol"),
+            Some(quoted(&params.get_meter_name(index)).as_str())
+        );
+        assert_eq!(number(port, "lv2:minimum"), *range.start());
+        assert_eq!(number(port, "lv2:maximum"), *range.end());
+    }
+}
+
+#[test]
+fn symbols_are_unique_c_identifiers() {
+    let ttl = plugin_ttl::<LoudnessLimiter>();
+    let mut symbols = HashSet::new();
+
+    for port in ports(&ttl) {
+        let symbol = property(&port, "lv2:symbol").unwrap().trim_matches('"');
+
+        assert!(
+            symbol.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                && symbol
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_'),
+            "{}",
+            symbol
+        );
+        assert!(symbols.insert(symbol.to_string()), "{} twice", symbol);
+    }
+}
@@ -0,0 +1,171 @@
+use vst::plugin::Plugin;
+
+use jimtel::test_host::{self, peak, TestHost};
+use loudness_limiter::LoudnessLimiter;
+
+const SAMPLE_RATE_HZ: f32 = 48000.0;
+
+fn sine(dbfs: f32, seconds: f32) -> Vec<Vec<f32>> {
+    test_host::sine(SAMPLE_RATE_HZ, dbfs, seconds)
+}
+
+fn host() -> TestHost<LoudnessLimiter> {
+    TestHost::new(SAMPLE_RATE_HZ, 2)
+}
+
+#[test]
+fn silence_stays_silent() {
+    let mut host = host();
+    let outputs = host.process_in_blocks(&vec![vec![0.0; 48000]; 2], 512);
+
+    assert!(outputs.iter().flatten().all(|&sample| sample == 0.0));
+}
+
+#[test]
+fn loud_input_is_brought_down_to_the_target() {
+    let mut host = host();
+    let outputs = host.process_in_blocks(&sine(-6.0, 5.0), 512);
+
+    // The last second, once the 1 s loudness window has settled.
+    let tail: Vec<Vec<f32>> = outputs
+        .iter()
+        .map(|output| output[192000..].to_vec())
+        .collect();
+    let lkfs = jimtel::normalize::measure(SAMPLE_RATE_HZ, &tail).integrated_lkfs;
+    assert!((lkfs - (-23.0)).abs() < 0.2, "{} LKFS", lkfs);
+
+    let input_lkfs = host.meter("input_loudness_pre_gain");
+    assert!((input_lkfs - (-6.0)).abs() < 0.2, "{} LKFS", input_lkfs);
+
+    let output_lkfs = host.meter("output_loudness_post_gain");
+    assert!((output_lkfs - (-23.0)).abs() < 0.2, "{} LKFS", output_lkfs);
+
+    let gain_reduction_db = host.meter("gain_reduction");
+    assert!(
+        (gain_reduction_db - (-17.0)).abs() < 0.2,
+        "{} dB",
+        gain_reduction_db
+    );
+}
+
+#[test]
+fn quiet_input_passes_unchanged_once_settled() {
+    let mut host = host();
+    let inputs = sine(-40.0, 3.0);
+    let outputs = host.process_in_blocks(&inputs, 512);
+
+    for (output, input) in outputs.iter().zip(&inputs) {
+        assert_eq!(output[96000..], input[96000..]);
+    }
+}
+
+#[test]
+fn params_follow_host_text() {
+    let mut host = host();
+    host.set_parameter_text("loudness", "-30");
+    let outputs = host.process_in_blocks(&sine(-6.0, 5.0), 512);
+
+    let tail: Vec<Vec<f32>> = outputs
+        .iter()
+        .map(|output| output[192000..].to_vec())
+        .collect();
+    let lkfs = jimtel::normalize::measure(SAMPLE_RATE_HZ, &tail).integrated_lkfs;
+    assert!((lkfs - (-30.0)).abs() < 0.2, "{} LKFS", lkfs);
+}
+
+#[test]
+fn delay_shifts_the_output_and_is_reported_as_latency() {
+    let mut host = host();
+    assert_eq!(host.latency_samples(), 0);
+
+    host.set_parameter_text("delay", "10");
+    let inputs = sine(-40.0, 3.0);
+    let outputs = host.process_in_blocks(&inputs, 512);
+    assert_eq!(host.latency_samples(), 480);
+
+    for (output, input) in outputs.iter().zip(&inputs) {
+        assert!(output[..480].iter().all(|&sample| sample == 0.0));
+        assert_eq!(output[96000..], input[96000 - 480..input.len() - 480]);
+    }
+
+    // The true-peak brickwall adds its lookahead.
+    host.set_parameter("true_peak_limit", 1.0);
+    host.process(&vec![vec![0.0; 32]; 2]);
+    assert!(host.latency_samples() > 480);
+}
+
+#[test]
+fn true_peak_limit_holds_the_ceiling() {
+    let mut host = host();
+    host.set_parameter_text("loudness", "0");
+    host.set_parameter_text("output_gain", "10");
+    host.set_parameter("true_peak_limit", 1.0);
+    let outputs = host.process_in_blocks(&sine(-6.0, 3.0), 512);
+
+    // -6 dBFS with 10 dB of output gain would peak at +4 dBFS.
+    let ceiling = 10f32.powf(-1.0 / 20.0);
+    assert!(peak(&outputs) <= ceiling + 1e-3, "{}", peak(&outputs));
+    assert!(peak(&outputs) > 0.5 * ceiling);
+
+    let max_true_peak_dbtp = host.meter("max_true_peak");
+    assert!(
+        max_true_peak_dbtp <= -1.0 + 0.1,
+        "{} dBTP",
+        max_true_peak_dbtp
+    );
+}
+
+#[test]
+fn reset_measurements_restarts_the_meters() {
+    let mut host = host();
+    host.process_in_blocks(&sine(-6.0, 3.0), 512);
+    assert!(host.meter("integrated_loudness") > -30.0);
+    assert!(host.meter("max_true_peak") > -30.0);
+
+    // The button toggles its value; any change resets.
+    host.set_parameter("reset_measurements", 1.0);
+    host.process_in_blocks(&vec![vec![0.0; 4800]; 2], 512);
+    // Both read their floor, below the bottom of the meter range.
+    assert!(host.meter("integrated_loudness") < -60.0);
+    assert!(host.meter("max_true_peak") < -60.0);
+}
+
+#[test]
+fn measurements_carry_on_across_a_resume() {
+    let mut host = host();
+    host.process_in_blocks(&sine(-6.0, 3.0), 512);
+    let integrated_lkfs = host.meter("integrated_loudness");
+    let max_true_peak_dbtp = host.meter("max_true_peak");
+
+    // As when the transport stops and starts again.
+    host.plugin().suspend();
+    host.plugin().set_sample_rate(SAMPLE_RATE_HZ);
+    host.plugin().resume();
+    host.process(&vec![vec![0.0; 32]; 2]);
+
+    assert_eq!(host.meter("integrated_loudness"), integrated_lkfs);
+    assert_eq!(host.meter("max_true_peak"), max_true_peak_dbtp);
+
+    // Another sample rate starts them afresh.
+    host.plugin().set_sample_rate(44100.0);
+    host.plugin().resume();
+    host.process_in_blocks(&vec![vec![0.0; 4800]; 2], 512);
+
+    assert!(host.meter("integrated_loudness") < -60.0);
+    assert!(host.meter("max_true_peak") < -60.0);
+}
+
+#[test]
+fn block_size_does_not_change_the_output() {
+    let inputs = sine(-6.0, 1.0);
+    let expected = host().process_in_blocks(&inputs, 512);
+
+    for block_size in [1, 31, 32, 100, 4096] {
+        assert_eq!(
+            host().process_in_blocks(&inputs, block_size),
+            expected,
+            "block size {}",
+            block_size
+        );
+    }
+}
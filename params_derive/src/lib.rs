@@ -1,7 +1,8 @@
 use darling::{ast::Data, FromDeriveInput, FromField, FromMeta};
 use proc_macro::TokenStream;
-use quote::quote;
-use syn::{parse_macro_input, DeriveInput, Ident};
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, DeriveInput, Ident, Visibility};
 
 #[derive(FromMeta)]
 enum Kind {
@@ -11,12 +12,17 @@ enum Kind {
     Db,
     #[darling(rename = "dBFS")]
     Dbfs,
+    #[darling(rename = "dBTP")]
+    Dbtp,
     #[darling(rename = "LKFS")]
     Lkfs,
+    #[darling(rename = "LU")]
+    Lu,
     #[darling(rename = "samples")]
     Samples,
     Button,
     Checkbox,
+    Choice,
 }
 
 impl Default for Kind {
@@ -25,10 +31,77 @@ impl Default for Kind {
     }
 }
 
+impl Kind {
+    fn unit(&self) -> &'static str {
+        match self {
+            Kind::None => "",
+            Kind::Ms => "ms",
+            Kind::Db => "dB",
+            Kind::Dbfs => "dBFS",
+            Kind::Dbtp => "dBTP",
+            Kind::Lkfs => "LKFS",
+            Kind::Lu => "LU",
+            Kind::Samples => "samples",
+            Kind::Button => "",
+            Kind::Checkbox => "",
+            Kind::Choice => "",
+        }
+    }
+
+    fn default_precision(&self) -> usize {
+        match self {
+            Kind::Samples | Kind::Button | Kind::Checkbox | Kind::Choice => 0,
+            _ => 1,
+        }
+    }
+}
+
+// How the 0..1 range of the host and the editor's slider maps onto min..max.
+#[derive(FromMeta)]
+enum Curve {
+    Linear,
+    Log,
+}
+
+impl Default for Curve {
+    fn default() -> Self {
+        Curve::Linear
+    }
+}
+
+// How a smoothed param ramps, mirroring `jimtel::smoother::Ramp`.
+#[derive(FromMeta)]
+enum Ramp {
+    Linear,
+    OnePole,
+}
+
+impl Default for Ramp {
+    fn default() -> Self {
+        Ramp::Linear
+    }
+}
+
+// A smoothing time such as "20ms".
+struct SmoothingTime(f32);
+
+impl FromMeta for SmoothingTime {
+    fn from_string(value: &str) -> darling::Result<Self> {
+        value
+            .strip_suffix("ms")
+            .and_then(|ms| ms.trim().parse().ok())
+            .map(SmoothingTime)
+            .ok_or_else(|| {
+                darling::Error::custom(format!("expected a time like \"20ms\", got \"{}\"", value))
+            })
+    }
+}
+
 #[derive(FromDeriveInput)]
 #[darling(supports(struct_named))]
 struct Input {
     ident: Ident,
+    vis: Visibility,
 
     data: Data<(), Field>,
 }
@@ -41,8 +114,27 @@ struct Field {
     #[darling(default)]
     kind: Kind,
 
-    min: f32,
-    max: f32,
+    // Required, with min below max, except by choice params, whose range is their
+    // list of choices.
+    #[darling(default)]
+    min: Option<f32>,
+    #[darling(default)]
+    max: Option<f32>,
+
+    // A choice param names a Rust enum implementing `jimtel::params::Choice`; it
+    // stores the index of the chosen variant.
+    #[darling(default)]
+    choices: Option<syn::Path>,
+
+    // Initial value in the unit shown to the user (e.g. "-23" for -23 LKFS, or the
+    // variant index of a choice), also what the editor resets to. Falls back to min.
+    #[darling(default)]
+    default: Option<f32>,
+
+    // Params sharing a non-empty section are shown together under its heading in
+    // the editor.
+    #[darling(default)]
+    section: String,
 
     // A meter is a read-only output value written by the audio thread and shown
     // in the editor. It is excluded from the VST parameter set (not automatable,
@@ -54,13 +146,179 @@ struct Field {
     // editor shows a selector and plots only the chosen one.
     #[darling(default)]
     group: String,
+
+    // Params with a smoothing time get a `jimtel::smoother::Smoother` in the
+    // generated `<Struct>Smoothers`, which the audio thread advances per sample.
+    #[darling(default)]
+    smooth: Option<SmoothingTime>,
+
+    #[darling(default)]
+    ramp: Ramp,
+
+    // Shapes the 0..1 range: `skew` raises the linear position to that power
+    // (below 1 gives the low end more room), `curve = "log"` spaces min..max
+    // evenly in ratio and needs a positive min.
+    #[darling(default)]
+    skew: Option<f32>,
+
+    #[darling(default)]
+    curve: Curve,
+
+    // Decimal places of the value as shown to the user; a ms value from 1000 up
+    // is shown in seconds with one more.
+    #[darling(default)]
+    precision: Option<usize>,
+}
+
+impl Field {
+    fn default_value(&self) -> f32 {
+        self.default.unwrap_or_else(|| self.min())
+    }
+
+    fn min(&self) -> f32 {
+        self.min.unwrap_or(0.0)
+    }
+
+    fn max(&self) -> f32 {
+        self.max.unwrap_or(0.0)
+    }
+
+    fn precision(&self) -> usize {
+        self.precision
+            .unwrap_or_else(|| self.kind.default_precision())
+    }
+
+    // The index of the last choice, as an f32 expression.
+    fn last_choice(&self) -> Option<TokenStream2> {
+        self.choices.as_ref().map(|choices| {
+            quote! { ((<#choices as jimtel::params::Choice>::NAMES.len().max(1) - 1) as f32) }
+        })
+    }
+
+    fn check_choices(&self) -> syn::Result<()> {
+        let ident = self.ident.as_ref().unwrap();
+
+        match (&self.kind, &self.choices) {
+            (Kind::Choice, None) => Err(syn::Error::new_spanned(
+                ident,
+                "kind = \"choice\" needs choices = \"SomeEnum\"",
+            )),
+            (Kind::Choice, Some(_)) => Ok(()),
+            (_, Some(_)) => Err(syn::Error::new_spanned(
+                ident,
+                "choices needs kind = \"choice\"",
+            )),
+            (_, None) => Ok(()),
+        }
+    }
+
+    fn check_range(&self) -> syn::Result<()> {
+        let ident = self.ident.as_ref().unwrap();
+
+        match (&self.kind, self.min, self.max) {
+            (Kind::Choice, _, _) => Ok(()),
+            (_, Some(min), Some(max)) if min >= max => {
+                Err(syn::Error::new_spanned(ident, "min must be below max"))
+            }
+            (_, Some(_), Some(_)) => Ok(()),
+            _ => Err(syn::Error::new_spanned(ident, "min and max are required")),
+        }
+    }
+
+    fn check_curve(&self) -> syn::Result<()> {
+        let ident = self.ident.as_ref().unwrap();
+
+        match (&self.curve, self.skew) {
+            (Curve::Log, Some(_)) => Err(syn::Error::new_spanned(
+                ident,
+                "skew and curve = \"log\" cannot be combined",
+            )),
+            (Curve::Log, None) if self.min() <= 0.0 => Err(syn::Error::new_spanned(
+                ident,
+                "curve = \"log\" needs a positive min",
+            )),
+            (Curve::Linear, Some(skew)) if skew <= 0.0 => {
+                Err(syn::Error::new_spanned(ident, "skew must be positive"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Maps `value`, in the unit shown to the user, onto 0..1. Choices take evenly
+    // spaced steps.
+    fn normalize(&self, value: TokenStream2) -> TokenStream2 {
+        if let Some(last_choice) = self.last_choice() {
+            return quote! { (#value) / #last_choice.max(1.0) };
+        }
+
+        let (min, max) = (self.min(), self.max());
+        let width = max - min;
+
+        match (&self.curve, self.skew) {
+            (Curve::Log, _) => quote! {
+                ((#value).max(#min) / #min).ln() / (#max / #min).ln()
+            },
+            (Curve::Linear, Some(skew)) => quote! {
+                (((#value) - #min) / #width).max(0.0).powf(#skew)
+            },
+            (Curve::Linear, None) => quote! { ((#value) - #min) / #width },
+        }
+    }
+
+    // Maps `normalized`, in 0..1, back onto min..max.
+    fn denormalize(&self, normalized: TokenStream2) -> TokenStream2 {
+        if let Some(last_choice) = self.last_choice() {
+            return quote! { ((#normalized) * #last_choice).round() };
+        }
+
+        let (min, max) = (self.min(), self.max());
+        let width = max - min;
+
+        match (&self.curve, self.skew) {
+            (Curve::Log, _) => quote! { #min * (#max / #min).powf(#normalized) },
+            (Curve::Linear, Some(skew)) => {
+                let exponent = 1.0 / skew;
+                quote! { #min + #width * (#normalized).max(0.0).powf(#exponent) }
+            }
+            (Curve::Linear, None) => quote! { #min + #width * (#normalized) },
+        }
+    }
+}
+
+// Converts a value in the unit shown to the user into the one stored in the field.
+fn to_stored_value(field: &Field, value: TokenStream2) -> TokenStream2 {
+    match field.kind {
+        Kind::Db | Kind::Dbfs | Kind::Dbtp => quote! { 10f32.powf((#value) / 20.0) },
+        Kind::Lkfs => quote! { 10f32.powf((#value + 0.691) / 10.0) },
+        Kind::Choice => {
+            let last_choice = field.last_choice();
+            quote! { (#value).round().max(0.0).min(#last_choice) }
+        }
+        _ => value,
+    }
+}
+
+// quote! cannot emit a literal for an infinite f32, which a default of "-inf" is.
+fn f32_tokens(value: f32) -> TokenStream2 {
+    if value == f32::INFINITY {
+        quote! { f32::INFINITY }
+    } else if value == f32::NEG_INFINITY {
+        quote! { f32::NEG_INFINITY }
+    } else {
+        quote! { #value }
+    }
 }
 
 #[proc_macro_derive(Params, attributes(param))]
 pub fn derive_plugin_parameters(input: TokenStream) -> TokenStream {
-    let input = Input::from_derive_input(&parse_macro_input!(input as DeriveInput)).unwrap();
+    let input = match Input::from_derive_input(&parse_macro_input!(input as DeriveInput)) {
+        Ok(input) => input,
+        Err(err) => return err.write_errors().into(),
+    };
 
     let ident = input.ident;
+    let vis = input.vis;
+    let smoothers_ident = format_ident!("{}Smoothers", ident);
 
     // Partition fields into meters and regular parameters, each with its own
     // 0-based, contiguous index space. Regular parameters keep the indices the
@@ -73,6 +331,16 @@ pub fn derive_plugin_parameters(input: TokenStream) -> TokenStream {
         .into_iter()
         .partition(|field| field.meter);
 
+    for field in param_fields.iter().chain(&meter_fields) {
+        if let Err(err) = field
+            .check_choices()
+            .and_then(|_| field.check_range())
+            .and_then(|_| field.check_curve())
+        {
+            return err.to_compile_error().into();
+        }
+    }
+
     let fields = param_fields
         .into_iter()
         .enumerate()
@@ -85,6 +353,11 @@ pub fn derive_plugin_parameters(input: TokenStream) -> TokenStream {
         .map(|(i, field)| (i as i32, field))
         .collect::<Vec<_>>();
 
+    let smoothed_fields = fields
+        .iter()
+        .filter_map(|(_, field)| field.smooth.as_ref().map(|smooth| (field, smooth.0)))
+        .collect::<Vec<_>>();
+
     let num_fields = fields.len();
     let num_meters = meters.len();
 
@@ -94,16 +367,7 @@ pub fn derive_plugin_parameters(input: TokenStream) -> TokenStream {
     });
 
     let get_unit_matches = fields.iter().map(|(i, field)| {
-        let unit = match field.kind {
-            Kind::None => "",
-            Kind::Ms => "ms",
-            Kind::Db => "dB",
-            Kind::Dbfs => "dBFS",
-            Kind::Lkfs => "LKFS",
-            Kind::Samples => "samples",
-            Kind::Button => "",
-            Kind::Checkbox => "",
-        };
+        let unit = field.kind.unit();
 
         quote! { #i => #unit.to_string() }
     });
@@ -126,16 +390,34 @@ pub fn derive_plugin_parameters(input: TokenStream) -> TokenStream {
         quote! { #i => #is_checkbox }
     });
 
-    let get_range_matches = fields.iter().map(|(i, field)| {
-        let Field { min, max, .. } = field;
-        quote! { #i => #min..=#max }
+    let get_range_matches = fields.iter().map(|(i, field)| match field.last_choice() {
+        Some(last_choice) => quote! { #i => 0.0..=#last_choice },
+        None => {
+            let (min, max) = (field.min(), field.max());
+            quote! { #i => #min..=#max }
+        }
+    });
+
+    let get_choices_matches = fields.iter().map(|(i, field)| match &field.choices {
+        Some(choices) => quote! {
+            #i => <#choices as jimtel::params::Choice>::NAMES
+                .iter()
+                .map(|name| name.to_string())
+                .collect()
+        },
+        None => quote! { #i => Vec::new() },
+    });
+
+    let get_section_matches = fields.iter().map(|(i, field)| {
+        let section = field.section.as_str();
+        quote! { #i => #section.to_string() }
     });
 
     let get_value_matches = fields.iter().map(|(i, field)| {
         let ident = field.ident.as_ref().unwrap();
 
         match field.kind {
-            Kind::Db | Kind::Dbfs => {
+            Kind::Db | Kind::Dbfs | Kind::Dbtp => {
                 quote! { #i => 20.0 * self.#ident.get().log10() }
             }
 
@@ -149,30 +431,49 @@ pub fn derive_plugin_parameters(input: TokenStream) -> TokenStream {
         }
     });
 
-    let get_value_text_matches = fields.iter().map(|(i, _field)| {
-        quote! { #i => ((10.0 * self.get_value(#i)).round() / 10.0).to_string() }
+    let value_to_text_matches = fields.iter().map(|(i, field)| match &field.choices {
+        Some(choices) => quote! {
+            #i => <#choices as jimtel::params::Choice>::NAMES
+                .get(value.max(0.0).round() as usize)
+                .map(|name| name.to_string())
+                .unwrap_or_default()
+        },
+        None => {
+            let unit = field.kind.unit();
+            let precision = field.precision();
+            quote! { #i => jimtel::params::format_value(value, #unit, #precision) }
+        }
+    });
+
+    // Choices are also accepted by name when the host hands text back.
+    let choice_from_text_matches = fields.iter().filter_map(|(i, field)| {
+        field.choices.as_ref().map(|choices| {
+            quote! {
+                #i => <#choices as jimtel::params::Choice>::NAMES
+                    .iter()
+                    .position(|name| name.eq_ignore_ascii_case(text.trim()))
+                    .map(|index| index as f32),
+            }
+        })
     });
 
     let set_value_matches = fields.iter().map(|(i, field)| {
         let ident = field.ident.as_ref().unwrap();
+        let value = to_stored_value(field, quote! { value });
 
-        match field.kind {
-            Kind::Db | Kind::Dbfs => {
-                quote! { #i => self.#ident.set(10f32.powf((value) / 20.0)) }
-            }
-
-            Kind::Lkfs => {
-                quote! { #i => self.#ident.set(10f32.powf((value + 0.691) / 10.0)) }
-            }
+        quote! { #i => self.#ident.set(#value) }
+    });
 
-            _ => {
-                quote! { #i => self.#ident.set(value) }
-            }
-        }
+    let get_default_matches = fields.iter().map(|(i, field)| {
+        let default = f32_tokens(field.default_value());
+        quote! { #i => #default }
     });
 
-    let get_parameter_label_matches = fields.iter().map(|(i, _field)| {
-        quote! { #i => self.get_unit(#i) }
+    let default_field_values = fields.iter().chain(meters.iter()).map(|(_, field)| {
+        let ident = field.ident.as_ref().unwrap();
+        let value = to_stored_value(field, f32_tokens(field.default_value()));
+
+        quote! { #ident: vst::util::AtomicFloat::new(#value) }
     });
 
     let get_parameter_text_matches = fields.iter().map(|(i, _field)| {
@@ -183,18 +484,22 @@ pub fn derive_plugin_parameters(input: TokenStream) -> TokenStream {
         quote! { #i => self.get_name(#i) }
     });
 
-    let get_paramater_matches = fields.iter().map(|(i, field)| {
-        let min = field.min;
-        let width = field.max - field.min;
+    let normalize_matches = fields.iter().map(|(i, field)| {
+        let normalized = field.normalize(quote! { value });
+        quote! { #i => (#normalized).min(1.0).max(0.0) }
+    });
 
-        quote! { #i => (self.get_value(#i) - #min) / #width }
+    let denormalize_matches = fields.iter().map(|(i, field)| {
+        let value = field.denormalize(quote! { normalized.min(1.0).max(0.0) });
+        quote! { #i => #value }
     });
 
-    let set_paramater_matches = fields.iter().map(|(i, field)| {
-        let min = field.min;
-        let width = field.max - field.min;
+    let get_paramater_matches = fields.iter().map(|(i, _field)| {
+        quote! { #i => self.normalize(#i, self.get_value(#i)) }
+    });
 
-        quote! { #i => self.set_value(#i, #min + #width * value)}
+    let set_paramater_matches = fields.iter().map(|(i, _field)| {
+        quote! { #i => self.set_value(#i, self.denormalize(#i, value)) }
     });
 
     // Meter arms end each match arm with its own trailing comma so the generated
@@ -205,16 +510,7 @@ pub fn derive_plugin_parameters(input: TokenStream) -> TokenStream {
     });
 
     let get_meter_unit_matches = meters.iter().map(|(i, field)| {
-        let unit = match field.kind {
-            Kind::None => "",
-            Kind::Ms => "ms",
-            Kind::Db => "dB",
-            Kind::Dbfs => "dBFS",
-            Kind::Lkfs => "LKFS",
-            Kind::Samples => "samples",
-            Kind::Button => "",
-            Kind::Checkbox => "",
-        };
+        let unit = field.kind.unit();
 
         quote! { #i => #unit.to_string(), }
     });
@@ -223,7 +519,7 @@ pub fn derive_plugin_parameters(input: TokenStream) -> TokenStream {
         let ident = field.ident.as_ref().unwrap();
 
         match field.kind {
-            Kind::Db | Kind::Dbfs => {
+            Kind::Db | Kind::Dbfs | Kind::Dbtp => {
                 quote! { #i => 20.0 * self.#ident.get().log10(), }
             }
 
@@ -237,12 +533,16 @@ pub fn derive_plugin_parameters(input: TokenStream) -> TokenStream {
         }
     });
 
-    let get_meter_value_text_matches = meters.iter().map(|(i, _field)| {
-        quote! { #i => ((10.0 * self.get_meter_value(#i)).round() / 10.0).to_string(), }
+    let get_meter_value_text_matches = meters.iter().map(|(i, field)| {
+        let unit = field.kind.unit();
+        let precision = field.precision();
+        quote! {
+            #i => jimtel::params::format_value(self.get_meter_value(#i), #unit, #precision),
+        }
     });
 
     let get_meter_range_matches = meters.iter().map(|(i, field)| {
-        let Field { min, max, .. } = field;
+        let (min, max) = (field.min(), field.max());
         quote! { #i => #min..=#max, }
     });
 
@@ -251,6 +551,36 @@ pub fn derive_plugin_parameters(input: TokenStream) -> TokenStream {
         quote! { #i => #group.to_string(), }
     });
 
+    let smoother_fields = smoothed_fields.iter().map(|(field, _)| {
+        let ident = field.ident.as_ref().unwrap();
+        quote! { pub #ident: jimtel::smoother::Smoother }
+    });
+
+    let new_smoothers = smoothed_fields.iter().map(|(field, smoothing_ms)| {
+        let ident = field.ident.as_ref().unwrap();
+        let ramp = match field.ramp {
+            Ramp::Linear => quote! { jimtel::smoother::Ramp::Linear },
+            Ramp::OnePole => quote! { jimtel::smoother::Ramp::OnePole },
+        };
+
+        quote! {
+            #ident: jimtel::smoother::Smoother::new(
+                #ramp,
+                sample_rate_hz,
+                #smoothing_ms,
+                self.#ident.get(),
+            )
+        }
+    });
+
+    let set_smoother_targets = smoothed_fields.iter().map(|(field, _)| {
+        let ident = field.ident.as_ref().unwrap();
+        quote! { self.#ident.set_target(params.#ident.get()); }
+    });
+
+    // The generated code runs inside a host process, so no method may panic: an
+    // index out of range gets a neutral fallback (an empty string, false, zero,
+    // or doing nothing) instead.
     (quote! {
         impl jimtel::params::Params for #ident {
             fn num_params() -> usize { #num_fields }
@@ -262,56 +592,134 @@ pub fn derive_plugin_parameters(input: TokenStream) -> TokenStream {
             fn get_name(&self, index: i32) -> String {
                 match index {
                     #(#get_name_matches),*,
-                    _ => panic!(),
+                    _ => String::new(),
                 }
             }
 
             fn get_unit(&self, index: i32) -> String {
                 match index {
                     #(#get_unit_matches),*,
-                    _ => panic!(),
+                    _ => String::new(),
                 }
             }
 
             fn is_button(&self, index: i32) -> bool {
                 match index {
                     #(#is_button_matches),*,
-                    _ => panic!(),
+                    _ => false,
                 }
             }
 
             fn is_checkbox(&self, index: i32) -> bool {
                 match index {
                     #(#is_checkbox_matches),*,
-                    _ => panic!(),
+                    _ => false,
                 }
             }
 
             fn get_range(&self, index: i32) -> std::ops::RangeInclusive<f32> {
                 match index {
                     #(#get_range_matches),*,
-                    _ => panic!(),
+                    _ => 0.0..=1.0,
+                }
+            }
+
+            fn get_choices(&self, index: i32) -> Vec<String> {
+                match index {
+                    #(#get_choices_matches),*,
+                    _ => Vec::new(),
+                }
+            }
+
+            fn get_section(&self, index: i32) -> String {
+                match index {
+                    #(#get_section_matches),*,
+                    _ => String::new(),
                 }
             }
 
             fn get_value(&self, index: i32) -> f32 {
                 match index {
                     #(#get_value_matches),*,
-                    _ => panic!(),
+                    _ => 0.0,
                 }
             }
 
             fn get_value_text(&self, index: i32) -> String {
-                match index {
-                    #(#get_value_text_matches),*,
-                    _ => panic!(),
-                }
+                self.value_to_text(index, self.get_value(index))
             }
 
             fn set_value(&self, index: i32, value: f32) {
                 match index {
                     #(#set_value_matches),*,
-                    _ => panic!(),
+                    _ => {},
+                }
+            }
+
+            fn normalize(&self, index: i32, value: f32) -> f32 {
+                match index {
+                    #(#normalize_matches),*,
+                    _ => 0.0,
+                }
+            }
+
+            fn denormalize(&self, index: i32, normalized: f32) -> f32 {
+                match index {
+                    #(#denormalize_matches),*,
+                    _ => 0.0,
+                }
+            }
+
+            fn value_to_text(&self, index: i32, value: f32) -> String {
+                match index {
+                    #(#value_to_text_matches),*,
+                    _ => String::new(),
+                }
+            }
+
+            fn text_to_value(&self, index: i32, text: &str) -> Option<f32> {
+                if !Self::index_range().contains(&index) {
+                    return None;
+                }
+
+                let value = match index {
+                    #(#choice_from_text_matches)*
+                    _ => None,
+                }
+                .or_else(|| {
+                    jimtel::params::parse_value_text(
+                        text,
+                        &self.get_unit(index),
+                        self.is_button(index) || self.is_checkbox(index),
+                    )
+                });
+
+                // Clamped as bank data is, so the "-∞ dB" shown for silence reads
+                // back as silence.
+                value.map(|value| {
+                    jimtel::params::clamp_restored_value(
+                        value,
+                        self.get_range(index),
+                        &self.get_unit(index),
+                    )
+                })
+            }
+
+            fn set_value_text(&self, index: i32, text: &str) -> bool {
+                match self.text_to_value(index, text) {
+                    Some(value) => {
+                        self.set_value(index, value);
+                        true
+                    }
+
+                    None => false,
+                }
+            }
+
+            fn get_default(&self, index: i32) -> f32 {
+                match index {
+                    #(#get_default_matches),*,
+                    _ => 0.0,
                 }
             }
 
@@ -324,62 +732,106 @@ pub fn derive_plugin_parameters(input: TokenStream) -> TokenStream {
             fn get_meter_name(&self, index: i32) -> String {
                 match index {
                     #(#get_meter_name_matches)*
-                    _ => panic!(),
+                    _ => String::new(),
                 }
             }
 
             fn get_meter_unit(&self, index: i32) -> String {
                 match index {
                     #(#get_meter_unit_matches)*
-                    _ => panic!(),
+                    _ => String::new(),
                 }
             }
 
             fn get_meter_value(&self, index: i32) -> f32 {
                 match index {
                     #(#get_meter_value_matches)*
-                    _ => panic!(),
+                    _ => 0.0,
                 }
             }
 
             fn get_meter_value_text(&self, index: i32) -> String {
                 match index {
                     #(#get_meter_value_text_matches)*
-                    _ => panic!(),
+                    _ => String::new(),
                 }
             }
 
             fn get_meter_range(&self, index: i32) -> std::ops::RangeInclusive<f32> {
                 match index {
                     #(#get_meter_range_matches)*
-                    _ => panic!(),
+                    _ => 0.0..=1.0,
                 }
             }
 
             fn get_meter_group(&self, index: i32) -> String {
                 match index {
                     #(#get_meter_group_matches)*
-                    _ => panic!(),
+                    _ => String::new(),
                 }
             }
         }
 
-        impl vst::plugin::PluginParameters for #ident {
-            fn get_parameter_label(&self, index: i32) -> String {
-                use jimtel::params::Params;
+        impl Default for #ident {
+            fn default() -> Self {
+                Self {
+                    #(#default_field_values),*
+                }
+            }
+        }
 
-                match index {
-                    #(#get_parameter_label_matches),*,
-                    _ => panic!(),
+        impl #ident {
+            pub fn new() -> Self {
+                Self::default()
+            }
+
+            // Smoothers for the params declared with `smooth`, starting at their
+            // current values.
+            #[allow(dead_code, unused_variables)]
+            pub fn smoothers(&self, sample_rate_hz: f32) -> #smoothers_ident {
+                #smoothers_ident {
+                    #(#new_smoothers),*
                 }
             }
 
+            // Sets a value read back from bank data, clamped to the range.
+            fn restore_value(&self, index: i32, value: f32) {
+                use jimtel::params::Params;
+
+                let value = jimtel::params::clamp_restored_value(
+                    value,
+                    self.get_range(index),
+                    &self.get_unit(index),
+                );
+                self.set_value(index, value);
+            }
+        }
+
+        #[allow(dead_code)]
+        #vis struct #smoothers_ident {
+            #(#smoother_fields),*
+        }
+
+        impl #smoothers_ident {
+            // Points every smoother at the current value of its param.
+            #[allow(dead_code, unused_variables)]
+            pub fn set_targets(&mut self, params: &#ident) {
+                #(#set_smoother_targets)*
+            }
+        }
+
+        impl vst::plugin::PluginParameters for #ident {
+            // The unit is part of the text, which may switch it (ms to s).
+            fn get_parameter_label(&self, _index: i32) -> String {
+                String::new()
+            }
+
             fn get_parameter_text(&self, index: i32) -> String {
                 use jimtel::params::Params;
 
                 match index {
                     #(#get_parameter_text_matches),*,
-                    _ => panic!(),
+                    _ => String::new(),
                 }
             }
 
@@ -388,7 +840,7 @@ pub fn derive_plugin_parameters(input: TokenStream) -> TokenStream {
 
                 match index {
                     #(#get_parameter_name_matches),*,
-                    _ => panic!(),
+                    _ => String::new(),
                 }
             }
 
@@ -397,7 +849,7 @@ pub fn derive_plugin_parameters(input: TokenStream) -> TokenStream {
 
                 match index {
                     #(#get_paramater_matches),*,
-                    _ => panic!(),
+                    _ => 0.0,
                 }
             }
 
@@ -406,45 +858,126 @@ pub fn derive_plugin_parameters(input: TokenStream) -> TokenStream {
 
                 match index {
                     #(#set_paramater_matches),*,
-                    _ => panic!()
+                    _ => {},
                 }
             }
 
             fn string_to_parameter(&self, index: i32, text: String) -> bool {
                 use jimtel::params::Params;
 
-                match text.parse::<f32>() {
-                    Ok(value) => {
-                        self.set_value(index, value);
-                        true
-                    }
-
-                    Err(_) => false
-                }
+                self.set_value_text(index, &text)
             }
 
+            // Bank data is `(version, values by param name)`, so adding, removing
+            // or reordering params keeps old presets intact.
             fn get_bank_data(&self) -> Vec<u8> {
                 use jimtel::params::Params;
 
-                let mut vec = vec![];
+                let mut values = std::collections::BTreeMap::new();
 
                 for index in Self::index_range() {
-                    vec.push(self.get_value(index));
+                    values.insert(self.get_name(index), self.get_value(index));
                 }
 
-                rmp_serde::to_vec(&vec).unwrap()
+                rmp_serde::to_vec(&(jimtel::params::BANK_DATA_VERSION, values)).unwrap_or_default()
             }
 
+            // Data that fails to decode, or comes from a newer version, is ignored as
+            // a whole, leaving the current values untouched, and NaN values are
+            // skipped one by one. The rest are clamped to their ranges; a silent dB
+            // value is saved as -inf and stays.
             fn load_bank_data(&self, data: &[u8]) {
                 use jimtel::params::Params;
 
-                let vec: Vec<f32> = rmp_serde::from_read_ref(data).unwrap();
+                let decoded: Result<(u32, std::collections::BTreeMap<String, f32>), _> =
+                    rmp_serde::from_read_ref(data);
 
-                for (index, value) in Self::index_range().zip(vec.into_iter()) {
-                    self.set_value(index, value);
+                if let Ok((version, values)) = decoded {
+                    // A newer build may have changed what the values mean.
+                    if version > jimtel::params::BANK_DATA_VERSION {
+                        return;
+                    }
+
+                    // Unknown names come from a newer or older build and are dropped;
+                    // params missing from the data keep their current values.
+                    for index in Self::index_range() {
+                        if let Some(&value) = values.get(&self.get_name(index)) {
+                            if !value.is_nan() {
+                                self.restore_value(index, value);
+                            }
+                        }
+                    }
+
+                    return;
+                }
+
+                // Version 0: a bare Vec<f32> in index order, as saved before the
+                // values were keyed by name.
+                let legacy: Result<Vec<f32>, _> = rmp_serde::from_read_ref(data);
+
+                if let Ok(vec) = legacy {
+                    for (index, value) in Self::index_range().zip(vec.into_iter()) {
+                        if !value.is_nan() {
+                            self.restore_value(index, value);
+                        }
+                    }
                 }
             }
         }
     })
     .into()
 }
+
+#[cfg(test)]
+mod tests {
+    use darling::FromField;
+    use quote::quote;
+    use syn::parse::Parser;
+
+    use super::Field;
+
+    fn field(tokens: proc_macro2::TokenStream) -> Field {
+        let field = syn::Field::parse_named.parse2(tokens).unwrap();
+        Field::from_field(&field).unwrap()
+    }
+
+    fn range_error(tokens: proc_macro2::TokenStream) -> Option<String> {
+        field(tokens).check_range().err().map(|err| err.to_string())
+    }
+
+    #[test]
+    fn min_below_max_is_accepted() {
+        assert_eq!(
+            range_error(quote! { #[param(min = "-60", max = "0")] gain: AtomicFloat }),
+            None
+        );
+    }
+
+    #[test]
+    fn min_not_below_max_is_rejected() {
+        assert_eq!(
+            range_error(quote! { #[param(min = "0", max = "-60")] gain: AtomicFloat }),
+            Some("min must be below max".to_string())
+        );
+        assert_eq!(
+            range_error(quote! { #[param(min = "0", max = "0")] gain: AtomicFloat }),
+            Some("min must be below max".to_string())
+        );
+    }
+
+    #[test]
+    fn min_and_max_are_required_unless_choice() {
+        assert_eq!(
+            range_error(quote! { #[param(max = "0")] gain: AtomicFloat }),
+            Some("min and max are required".to_string())
+        );
+        assert_eq!(
+            range_error(quote! { #[param(kind = "checkbox")] bypass: AtomicFloat }),
+            Some("min and max are required".to_string())
+        );
+        assert_eq!(
+            range_error(quote! { #[param(kind = "choice", choices = "Mode")] mode: AtomicFloat }),
+            None
+        );
+    }
+}
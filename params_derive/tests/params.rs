@@ -0,0 +1,524 @@
+use std::collections::BTreeMap;
+
+use vst::plugin::PluginParameters;
+use vst::util::AtomicFloat;
+
+use jimtel::params::{Choice, Params, BANK_DATA_VERSION};
+use params_derive::Params;
+
+#[derive(Params)]
+struct TestParams {
+    #[param(kind = "dB", min = "-80", max = "80", default = "0")]
+    gain: AtomicFloat,
+
+    #[param(kind = "ms", min = "0", max = "1000", default = "100")]
+    time: AtomicFloat,
+
+    #[param(kind = "dBTP", min = "-60", max = "6", default = "0", meter)]
+    peak: AtomicFloat,
+}
+
+#[derive(Params)]
+struct FallbackParams {
+    #[param(kind = "LKFS", min = "-80", max = "0")]
+    target: AtomicFloat,
+
+    #[param(kind = "dB", min = "-80", max = "0", default = "-inf")]
+    floor: AtomicFloat,
+}
+
+#[derive(Params)]
+struct SmoothedParams {
+    #[param(kind = "dB", min = "-80", max = "0", default = "0", smooth = "1ms")]
+    gain: AtomicFloat,
+
+    #[param(
+        kind = "ms",
+        min = "0",
+        max = "1000",
+        smooth = "1ms",
+        ramp = "one_pole"
+    )]
+    time: AtomicFloat,
+}
+
+#[derive(Params)]
+struct ShapedParams {
+    #[param(kind = "ms", min = "1", max = "1000", curve = "log")]
+    window: AtomicFloat,
+
+    #[param(kind = "ms", min = "0", max = "1000", skew = "0.5")]
+    release: AtomicFloat,
+}
+
+#[derive(Debug, PartialEq)]
+enum Mode {
+    Soft,
+    Medium,
+    Hard,
+}
+
+impl Choice for Mode {
+    const NAMES: &'static [&'static str] = &["soft", "medium", "hard"];
+
+    fn from_index(index: usize) -> Self {
+        match index {
+            0 => Mode::Soft,
+            1 => Mode::Medium,
+            _ => Mode::Hard,
+        }
+    }
+}
+
+#[derive(Params)]
+struct ChoiceParams {
+    #[param(kind = "choice", choices = "Mode", default = "1")]
+    mode: AtomicFloat,
+}
+
+#[derive(Params)]
+struct UnitParams {
+    #[param(kind = "LKFS", min = "-80", max = "0")]
+    target: AtomicFloat,
+
+    #[param(kind = "samples", min = "0", max = "1000")]
+    offset: AtomicFloat,
+
+    #[param(kind = "checkbox", min = "0", max = "1")]
+    enabled: AtomicFloat,
+
+    #[param(kind = "dBTP", min = "-20", max = "0")]
+    ceiling: AtomicFloat,
+
+    #[param(kind = "LU", min = "0", max = "30")]
+    range: AtomicFloat,
+}
+
+#[derive(Params)]
+struct SectionParams {
+    #[param(kind = "dB", min = "-80", max = "0", section = "Gain")]
+    gain: AtomicFloat,
+
+    #[param(kind = "ms", min = "0", max = "1000")]
+    time: AtomicFloat,
+}
+
+#[derive(Params)]
+struct PrecisionParams {
+    #[param(
+        kind = "ms",
+        min = "0",
+        max = "5000",
+        default = "1500",
+        precision = "2"
+    )]
+    release: AtomicFloat,
+
+    #[param(kind = "samples", min = "0", max = "1000", default = "64", meter)]
+    latency: AtomicFloat,
+}
+
+#[test]
+fn defaults_are_declared_in_display_units() {
+    let params = TestParams::new();
+
+    assert_eq!(params.gain.get(), 1.0);
+    assert_eq!(params.get_default(0), 0.0);
+    assert_eq!(params.time.get(), 100.0);
+    assert_eq!(params.get_default(1), 100.0);
+    assert_eq!(params.peak.get(), 1.0);
+}
+
+#[test]
+fn missing_defaults_fall_back_to_min() {
+    let params = FallbackParams::new();
+
+    assert_eq!(params.get_default(0), -80.0);
+    assert!((params.get_value(0) + 80.0).abs() < 1e-3);
+
+    assert_eq!(params.get_default(1), f32::NEG_INFINITY);
+    assert_eq!(params.floor.get(), 0.0);
+}
+
+#[test]
+fn out_of_range_params_get_fallbacks() {
+    let params = TestParams::new();
+
+    for index in [-1, TestParams::num_params() as i32, i32::MAX] {
+        assert_eq!(params.get_name(index), "");
+        assert_eq!(params.get_unit(index), "");
+        assert!(!params.is_button(index));
+        assert!(!params.is_checkbox(index));
+        assert_eq!(params.get_value(index), 0.0);
+        assert_eq!(params.get_value_text(index), "");
+        assert_eq!(params.get_default(index), 0.0);
+        params.set_value(index, 10.0);
+
+        assert_eq!(params.get_parameter_name(index), "");
+        assert_eq!(params.get_parameter_label(index), "");
+        assert_eq!(params.get_parameter_text(index), "");
+        assert_eq!(params.get_parameter(index), 0.0);
+        params.set_parameter(index, 0.5);
+        assert!(!params.string_to_parameter(index, "10".to_string()));
+    }
+
+    assert_eq!(params.gain.get(), 1.0);
+    assert_eq!(params.time.get(), 100.0);
+}
+
+#[test]
+fn out_of_range_meters_get_fallbacks() {
+    let params = TestParams::new();
+
+    for index in [-1, TestParams::num_meters() as i32, i32::MAX] {
+        assert_eq!(params.get_meter_name(index), "");
+        assert_eq!(params.get_meter_unit(index), "");
+        assert_eq!(params.get_meter_value(index), 0.0);
+        assert_eq!(params.get_meter_value_text(index), "");
+        assert_eq!(params.get_meter_group(index), "");
+    }
+}
+
+#[test]
+fn bank_data_round_trip() {
+    let params = TestParams::new();
+    params.set_value(0, -6.0);
+    params.set_value(1, 250.0);
+
+    let data = params.get_bank_data();
+
+    let restored = TestParams::new();
+    restored.load_bank_data(&data);
+    assert!((restored.get_value(0) + 6.0).abs() < 1e-4);
+    assert_eq!(restored.get_value(1), 250.0);
+}
+
+#[test]
+fn silent_gain_survives_bank_data() {
+    let params = TestParams::new();
+    params.gain.set(0.0);
+
+    let restored = TestParams::new();
+    restored.load_bank_data(&params.get_bank_data());
+    assert_eq!(restored.gain.get(), 0.0);
+}
+
+#[test]
+fn malformed_bank_data_is_ignored() {
+    let params = TestParams::new();
+
+    let malformed = [
+        vec![],
+        vec![0xc1],
+        vec![0x92, 0xca],
+        rmp_serde::to_vec(&"not a bank").unwrap(),
+        rmp_serde::to_vec(&vec!["a", "b"]).unwrap(),
+    ];
+
+    for data in &malformed {
+        params.load_bank_data(data);
+        assert_eq!(params.gain.get(), 1.0);
+        assert_eq!(params.time.get(), 100.0);
+    }
+}
+
+#[test]
+fn nan_values_in_bank_data_are_skipped() {
+    let params = TestParams::new();
+
+    params.load_bank_data(&rmp_serde::to_vec(&vec![f32::NAN, 500.0]).unwrap());
+    assert_eq!(params.gain.get(), 1.0);
+    assert_eq!(params.time.get(), 500.0);
+
+    let mut values = BTreeMap::new();
+    values.insert("time".to_string(), f32::NAN);
+    params.load_bank_data(&rmp_serde::to_vec(&(BANK_DATA_VERSION, values)).unwrap());
+    assert_eq!(params.time.get(), 500.0);
+}
+
+#[test]
+fn out_of_range_bank_data_is_clamped() {
+    let params = TestParams::new();
+
+    let mut values = BTreeMap::new();
+    values.insert("gain".to_string(), 1e9f32);
+    values.insert("time".to_string(), 1e9f32);
+    params.load_bank_data(&rmp_serde::to_vec(&(BANK_DATA_VERSION, values)).unwrap());
+    assert!((params.get_value(0) - 80.0).abs() < 1e-3);
+    assert_eq!(params.time.get(), 1000.0);
+
+    params.load_bank_data(&rmp_serde::to_vec(&vec![-1e9f32, f32::NEG_INFINITY]).unwrap());
+    assert!((params.get_value(0) + 80.0).abs() < 1e-3);
+    assert_eq!(params.time.get(), 0.0);
+}
+
+#[test]
+fn bank_data_from_a_newer_version_is_ignored() {
+    let params = TestParams::new();
+
+    let mut values = BTreeMap::new();
+    values.insert("gain".to_string(), 2.0f32);
+    values.insert("time".to_string(), 500.0f32);
+    let version = BANK_DATA_VERSION + 1;
+    params.load_bank_data(&rmp_serde::to_vec(&(version, values)).unwrap());
+
+    assert_eq!(params.gain.get(), 1.0);
+    assert_eq!(params.time.get(), 100.0);
+}
+
+#[test]
+fn short_bank_data_sets_the_leading_params() {
+    let params = TestParams::new();
+
+    params.load_bank_data(&rmp_serde::to_vec(&vec![-20.0f32]).unwrap());
+    assert!((params.get_value(0) + 20.0).abs() < 1e-4);
+    assert_eq!(params.time.get(), 100.0);
+}
+
+#[test]
+fn bank_data_is_keyed_by_name() {
+    let params = TestParams::new();
+    params.set_value(1, 250.0);
+
+    let (version, values): (u32, BTreeMap<String, f32>) =
+        rmp_serde::from_read_ref(&params.get_bank_data()).unwrap();
+    assert_eq!(version, BANK_DATA_VERSION);
+    assert_eq!(values.len(), 2);
+    assert_eq!(values["time"], 250.0);
+    assert!(!values.contains_key("peak"));
+}
+
+#[test]
+fn bank_data_from_another_layout_is_matched_by_name() {
+    let params = TestParams::new();
+
+    // Saved by a build that had a param before `time` and has no `gain`.
+    let mut values = BTreeMap::new();
+    values.insert("inserted".to_string(), 3.0f32);
+    values.insert("time".to_string(), 400.0f32);
+    params.load_bank_data(&rmp_serde::to_vec(&(BANK_DATA_VERSION, values)).unwrap());
+
+    assert_eq!(params.gain.get(), 1.0);
+    assert_eq!(params.time.get(), 400.0);
+}
+
+#[test]
+fn legacy_positional_bank_data_is_still_read() {
+    let params = TestParams::new();
+
+    params.load_bank_data(&rmp_serde::to_vec(&vec![-6.0f32, 250.0]).unwrap());
+    assert!((params.get_value(0) + 6.0).abs() < 1e-4);
+    assert_eq!(params.time.get(), 250.0);
+}
+
+#[test]
+fn smoothers_ramp_toward_the_params() {
+    let params = SmoothedParams::new();
+    let mut smoothers = params.smoothers(48000.0);
+    assert_eq!(smoothers.gain.current(), 1.0);
+
+    params.gain.set(0.5);
+    params.time.set(100.0);
+    smoothers.set_targets(&params);
+
+    let first = smoothers.gain.next_value();
+    assert!(first < 1.0 && first > 0.98, "{}", first);
+    assert!(smoothers.time.next_value() < 100.0);
+
+    for _ in 0..47 {
+        smoothers.gain.next_value();
+    }
+    assert_eq!(smoothers.gain.current(), 0.5);
+}
+
+#[test]
+fn shaped_params_round_trip_through_the_host_range() {
+    let params = ShapedParams::new();
+
+    assert_eq!(params.normalize(0, 1.0), 0.0);
+    assert!((params.normalize(0, 31.622776) - 0.5).abs() < 1e-5);
+    assert!((params.denormalize(0, 1.0) - 1000.0).abs() < 1e-2);
+
+    assert!((params.normalize(1, 250.0) - 0.5).abs() < 1e-6);
+    assert!((params.denormalize(1, 0.5) - 250.0).abs() < 1e-3);
+
+    params.set_parameter(1, 0.5);
+    assert!((params.release.get() - 250.0).abs() < 1e-3);
+    assert!((params.get_parameter(1) - 0.5).abs() < 1e-6);
+
+    // Out-of-range values are clamped to the host range.
+    assert_eq!(params.normalize(0, 0.0), 0.0);
+    assert_eq!(params.normalize(1, 2000.0), 1.0);
+    assert_eq!(
+        params.get_parameter(0),
+        params.normalize(0, params.window.get())
+    );
+}
+
+#[test]
+fn choice_params_step_through_their_variants() {
+    let params = ChoiceParams::new();
+
+    assert_eq!(params.get_range(0), 0.0..=2.0);
+    assert_eq!(params.get_choices(0), vec!["soft", "medium", "hard"]);
+    assert_eq!(params.get_parameter(0), 0.5);
+    assert_eq!(params.get_parameter_text(0), "medium");
+
+    params.set_parameter(0, 0.8);
+    assert_eq!(params.mode.get(), 2.0);
+    assert_eq!(Mode::from_value(params.mode.get()), Mode::Hard);
+
+    // Anything in between snaps to a variant and stays in range.
+    params.set_value(0, 0.4);
+    assert_eq!(params.mode.get(), 0.0);
+    params.set_value(0, 7.0);
+    assert_eq!(params.mode.get(), 2.0);
+
+    assert!(params.string_to_parameter(0, " Medium ".to_string()));
+    assert_eq!(params.mode.get(), 1.0);
+    assert!(params.string_to_parameter(0, "0".to_string()));
+    assert_eq!(params.mode.get(), 0.0);
+    assert!(!params.string_to_parameter(0, "loud".to_string()));
+}
+
+#[test]
+fn text_is_parsed_in_the_unit_of_the_param() {
+    let params = TestParams::new();
+
+    assert!(params.string_to_parameter(0, "-6dB".to_string()));
+    assert!((params.get_value(0) + 6.0).abs() < 1e-4);
+    assert!(params.string_to_parameter(0, "+3 DB".to_string()));
+    assert!((params.get_value(0) - 3.0).abs() < 1e-4);
+
+    assert!(params.string_to_parameter(1, "0.25s".to_string()));
+    assert_eq!(params.time.get(), 250.0);
+    assert!(params.string_to_parameter(1, "40 ms".to_string()));
+    assert_eq!(params.time.get(), 40.0);
+    assert!(params.string_to_parameter(1, "12".to_string()));
+    assert_eq!(params.time.get(), 12.0);
+
+    let params = UnitParams::new();
+
+    assert!(params.string_to_parameter(0, "-23 LUFS".to_string()));
+    assert!((params.get_value(0) + 23.0).abs() < 1e-3);
+    assert!(params.string_to_parameter(0, "-18lkfs".to_string()));
+    assert!((params.get_value(0) + 18.0).abs() < 1e-3);
+
+    assert!(params.string_to_parameter(1, "64 samples".to_string()));
+    assert_eq!(params.offset.get(), 64.0);
+
+    assert!(params.string_to_parameter(2, "on".to_string()));
+    assert_eq!(params.enabled.get(), 1.0);
+    assert!(params.string_to_parameter(2, "Off".to_string()));
+    assert_eq!(params.enabled.get(), 0.0);
+
+    assert!(params.string_to_parameter(3, "-1 dBTP".to_string()));
+    assert!((params.get_value(3) + 1.0).abs() < 1e-4);
+
+    assert!(params.string_to_parameter(4, "7.5 LU".to_string()));
+    assert_eq!(params.range.get(), 7.5);
+}
+
+#[test]
+fn parsed_text_is_clamped_to_the_range() {
+    let params = TestParams::new();
+
+    assert!(params.string_to_parameter(1, "1.5s".to_string()));
+    assert_eq!(params.time.get(), 1000.0);
+    assert!(params.string_to_parameter(1, "-5ms".to_string()));
+    assert_eq!(params.time.get(), 0.0);
+
+    assert!(params.string_to_parameter(0, "100 dB".to_string()));
+    assert!((params.get_value(0) - 80.0).abs() < 1e-3);
+}
+
+#[test]
+fn silence_text_round_trips() {
+    let params = TestParams::new();
+
+    for text in ["-∞ dB", "-inf dB"] {
+        params.set_value(0, 0.0);
+        assert!(params.string_to_parameter(0, text.to_string()), "{}", text);
+        assert_eq!(params.get_value(0), f32::NEG_INFINITY, "{}", text);
+        assert_eq!(params.gain.get(), 0.0, "{}", text);
+    }
+
+    assert_eq!(params.get_value_text(0), "-∞ dB");
+    assert_eq!(params.text_to_value(0, "-∞ dB"), Some(f32::NEG_INFINITY));
+}
+
+#[test]
+fn unparsable_text_is_rejected() {
+    let params = TestParams::new();
+
+    for text in ["", "loud", "6 LU", "nan", "on", "1.5 s s"] {
+        assert!(!params.string_to_parameter(0, text.to_string()), "{}", text);
+    }
+    assert_eq!(params.gain.get(), 1.0);
+
+    // A ms param does not take a level.
+    assert!(!params.string_to_parameter(1, "-6dB".to_string()));
+    assert_eq!(params.time.get(), 100.0);
+}
+
+#[test]
+fn values_are_formatted_with_their_unit() {
+    let params = TestParams::new();
+
+    assert_eq!(params.get_value_text(0), "0.0 dB");
+    assert_eq!(params.get_value_text(1), "100.0 ms");
+    assert_eq!(params.get_meter_value_text(0), "0.0 dBTP");
+    assert_eq!(params.get_parameter_label(0), "");
+
+    params.gain.set(0.0);
+    assert_eq!(params.get_value_text(0), "-∞ dB");
+
+    // Tiny negative values do not show as "-0.0".
+    params.set_value(0, -0.01);
+    assert_eq!(params.get_value_text(0), "0.0 dB");
+
+    let params = FallbackParams::new();
+    assert_eq!(params.get_value_text(0), "-80.0 LKFS");
+    assert_eq!(params.get_value_text(1), "-∞ dB");
+}
+
+#[test]
+fn precision_can_be_set_per_param() {
+    let params = PrecisionParams::new();
+
+    // A second and longer is shown in seconds, one digit finer.
+    assert_eq!(params.get_value_text(0), "1.500 s");
+    params.release.set(250.0);
+    assert_eq!(params.get_value_text(0), "250.00 ms");
+
+    assert_eq!(params.get_meter_value_text(0), "64 samples");
+
+    // What is shown can be typed back in.
+    assert!(params.set_value_text(0, "1.500 s"));
+    assert_eq!(params.release.get(), 1500.0);
+}
+
+#[test]
+fn params_report_their_section() {
+    let params = SectionParams::new();
+
+    assert_eq!(params.get_section(0), "Gain");
+    assert_eq!(params.get_section(1), "");
+    assert_eq!(params.get_section(2), "");
+}
+
+#[test]
+fn text_converts_without_setting_the_value() {
+    let params = TestParams::new();
+
+    // Format wrappers only hold the params as a trait object.
+    let dyn_params: &dyn Params = &params;
+
+    assert_eq!(dyn_params.value_to_text(1, 1500.0), "1.50 s");
+    assert_eq!(dyn_params.value_to_text(0, f32::NEG_INFINITY), "-∞ dB");
+    assert_eq!(dyn_params.text_to_value(1, "2 s"), Some(1000.0));
+    assert_eq!(dyn_params.text_to_value(1, "loud"), None);
+    assert_eq!(dyn_params.text_to_value(3, "1"), None);
+
+    assert_eq!(params.time.get(), 100.0);
+}
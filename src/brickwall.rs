@@ -0,0 +1,280 @@
+use std::collections::VecDeque;
+
+use crate::delay_buffer::DelayBuffer;
+use crate::sum_buffer::SumBuffer;
+use crate::true_peak::TruePeak;
+
+// The true-peak detector reports the peak around a sample about 6 samples after
+// it came in, and its interpolation filter spans 12 samples. The gain is held over
+// that span so the neighbours shaping an inter-sample peak are attenuated alike.
+const TRUE_PEAK_DELAY: usize = 6;
+const TRUE_PEAK_SPAN: usize = 12;
+
+/// Lookahead limiter keeping the true peak of its output at or below a ceiling.
+///
+/// The required gain is held for the lookahead and smoothed by a moving average of
+/// the same length, so the gain has ramped all the way down by the time a peak
+/// leaves the delay line.
+pub struct Brickwall {
+    sample_rate_hz: f32,
+    samples_num_per_lookahead: usize,
+
+    ceiling: f32,
+    release_coefficient: f32,
+
+    true_peak: TruePeak,
+
+    // Candidates for the minimum required gain over the hold window, as (sample
+    // number, gain) with the gains rising from front to back. Never longer than the
+    // window, its capacity, so it does not reallocate.
+    held_gains: VecDeque<(u64, f32)>,
+    samples_num_per_hold: u64,
+    samples_num: u64,
+
+    released_gain: f32,
+    gain_sum_buffer: SumBuffer,
+
+    delay_buffer: DelayBuffer,
+}
+
+impl Brickwall {
+    pub fn new(sample_rate_hz: f32, channels_num: usize, lookahead_ms: f32) -> Self {
+        let samples_num_per_lookahead = ((lookahead_ms / 1000.0 * sample_rate_hz) as usize).max(1);
+        let samples_num_per_hold = samples_num_per_lookahead + 1 + TRUE_PEAK_SPAN;
+
+        let mut brickwall = Self {
+            sample_rate_hz,
+            samples_num_per_lookahead,
+
+            ceiling: 1.0,
+            release_coefficient: 0.0,
+
+            true_peak: TruePeak::new(channels_num),
+
+            held_gains: VecDeque::with_capacity(samples_num_per_hold),
+            samples_num_per_hold: samples_num_per_hold as u64,
+            samples_num: 0,
+
+            released_gain: 1.0,
+            gain_sum_buffer: SumBuffer::new(samples_num_per_lookahead),
+
+            delay_buffer: DelayBuffer::new(
+                channels_num,
+                samples_num_per_lookahead + TRUE_PEAK_DELAY,
+            ),
+        };
+
+        brickwall.reset();
+        brickwall
+    }
+
+    /// Forgets the audio seen so far without allocating, so it can be called on the
+    /// audio thread.
+    pub fn reset(&mut self) {
+        self.true_peak.reset();
+
+        self.held_gains.clear();
+        self.samples_num = 0;
+
+        // Start from unity gain rather than fading in from silence.
+        self.released_gain = 1.0;
+        self.gain_sum_buffer.reset();
+        for _ in 0..self.samples_num_per_lookahead {
+            self.gain_sum_buffer.add(1.0);
+        }
+
+        self.delay_buffer.reset();
+    }
+
+    /// Returns how many samples the output lags behind the input.
+    pub fn latency(&self) -> usize {
+        self.samples_num_per_lookahead + TRUE_PEAK_DELAY
+    }
+
+    /// Sets the ceiling as an amplitude.
+    pub fn set_ceiling(&mut self, ceiling: f32) {
+        self.ceiling = ceiling;
+    }
+
+    pub fn set_release(&mut self, release_ms: f32) {
+        let release_samples = release_ms / 1000.0 * self.sample_rate_hz;
+
+        self.release_coefficient = if release_samples > 0.0 {
+            (-1.0 / release_samples).exp()
+        } else {
+            0.0
+        };
+    }
+
+    /// Takes one sample per channel and replaces them with the limited ones from
+    /// `latency()` samples ago.
+    #[inline(always)]
+    pub fn add_samples(&mut self, samples: &mut [f32]) {
+        let peak = self.true_peak.add_samples(samples);
+        let required_gain = if peak > self.ceiling {
+            self.ceiling / peak
+        } else {
+            1.0
+        };
+
+        // The running minimum: the front leaves once it is out of the window, which
+        // is checked before the new gain comes in so the deque never outgrows it,
+        // and gains no lower than the new one can never be the minimum again.
+        let window_start = (self.samples_num + 1).saturating_sub(self.samples_num_per_hold);
+        while matches!(self.held_gains.front(), Some(&(n, _)) if n < window_start) {
+            self.held_gains.pop_front();
+        }
+        while matches!(self.held_gains.back(), Some(&(_, gain)) if gain >= required_gain) {
+            self.held_gains.pop_back();
+        }
+        self.held_gains.push_back((self.samples_num, required_gain));
+        self.samples_num += 1;
+
+        let held_gain = self.held_gains.front().map_or(1.0, |&(_, gain)| gain);
+
+        // Attack at once (the moving average ramps it), release exponentially.
+        self.released_gain = if held_gain < self.released_gain {
+            held_gain
+        } else {
+            held_gain + (self.released_gain - held_gain) * self.release_coefficient
+        };
+
+        let gain = (self.gain_sum_buffer.add(self.released_gain)
+            / self.samples_num_per_lookahead as f32)
+            .min(1.0);
+
+        let delayed_samples = self.delay_buffer.add(samples);
+
+        for (sample, delayed_sample) in samples.iter_mut().zip(delayed_samples) {
+            *sample = delayed_sample * gain;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Brickwall;
+    use crate::true_peak::TruePeak;
+    use std::f32::consts::PI;
+
+    // Runs the signal through a brickwall at the given ceiling, returning the max
+    // true peak of the output in dBTP.
+    fn output_dbtp(ceiling_dbtp: f32, signal: impl Iterator<Item = f32>) -> f32 {
+        let mut brickwall = Brickwall::new(48000.0, 2, 1.5);
+        brickwall.set_ceiling(10f32.powf(ceiling_dbtp / 20.0));
+        brickwall.set_release(100.0);
+
+        let mut true_peak = TruePeak::new(2);
+        let mut max_peak = 0.0f32;
+        for sample in signal {
+            let mut samples = [sample, sample];
+            brickwall.add_samples(&mut samples);
+            max_peak = max_peak.max(true_peak.add_samples(&samples));
+        }
+
+        20.0 * max_peak.log10()
+    }
+
+    #[test]
+    fn inter_sample_peaks_are_kept_below_the_ceiling() {
+        let signal = (0..48000).map(|n| (2.0 * PI * 12000.0 * n as f32 / 48000.0 + PI / 4.0).sin());
+
+        let dbtp = output_dbtp(-1.0, signal);
+        assert!(dbtp <= -1.0 + 0.05, "{} dBTP", dbtp);
+        assert!(dbtp > -1.5, "{} dBTP", dbtp);
+    }
+
+    #[test]
+    fn sudden_bursts_are_kept_below_the_ceiling() {
+        // 10 ms bursts at +6 dBFS every 100 ms.
+        let signal = (0..48000).map(|n| {
+            if n % 4800 < 480 {
+                2.0 * (2.0 * PI * 3000.0 * n as f32 / 48000.0).sin()
+            } else {
+                0.0
+            }
+        });
+
+        let dbtp = output_dbtp(-1.0, signal);
+        assert!(dbtp <= -1.0 + 0.05, "{} dBTP", dbtp);
+    }
+
+    #[test]
+    fn noise_is_kept_below_the_ceiling() {
+        let mut state = 1u32;
+        let signal = (0..48000).map(move |_| {
+            state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+            4.0 * (state as f32 / u32::MAX as f32 - 0.5)
+        });
+
+        let dbtp = output_dbtp(-1.0, signal);
+        assert!(dbtp <= -1.0 + 0.05, "{} dBTP", dbtp);
+    }
+
+    #[test]
+    fn reset_starts_afresh() {
+        let signal: Vec<f32> = (0..4800)
+            .map(|n| 2.0 * (2.0 * PI * 3000.0 * n as f32 / 48000.0).sin())
+            .collect();
+
+        let run = |brickwall: &mut Brickwall| -> Vec<f32> {
+            signal
+                .iter()
+                .map(|&sample| {
+                    let mut samples = [sample, sample];
+                    brickwall.add_samples(&mut samples);
+                    samples[0]
+                })
+                .collect()
+        };
+
+        let mut brickwall = Brickwall::new(48000.0, 2, 1.5);
+        brickwall.set_ceiling(0.5);
+        brickwall.set_release(100.0);
+        let expected = run(&mut brickwall);
+
+        brickwall.reset();
+        assert_eq!(run(&mut brickwall), expected);
+    }
+
+    #[test]
+    fn held_gains_stay_within_their_capacity() {
+        let mut brickwall = Brickwall::new(48000.0, 2, 1.5);
+        brickwall.set_ceiling(0.5);
+        let capacity = brickwall.held_gains.capacity();
+
+        // A fading signal over the ceiling needs less and less gain reduction, so
+        // every required gain is kept until it leaves the window.
+        for n in 0..4800 {
+            let sample = 4.0 - 3.0 * n as f32 / 4800.0;
+            brickwall.add_samples(&mut [sample, sample]);
+        }
+
+        assert_eq!(brickwall.held_gains.capacity(), capacity);
+    }
+
+    #[test]
+    fn quiet_signal_is_only_delayed() {
+        let mut brickwall = Brickwall::new(48000.0, 2, 1.5);
+        brickwall.set_ceiling(1.0);
+        brickwall.set_release(100.0);
+
+        let latency = brickwall.latency();
+        let signal: Vec<f32> = (0..4800)
+            .map(|n| 0.5 * (2.0 * PI * 1000.0 * n as f32 / 48000.0).sin())
+            .collect();
+
+        for (n, &sample) in signal.iter().enumerate() {
+            let mut samples = [sample, -sample];
+            brickwall.add_samples(&mut samples);
+            let expected = if n >= latency {
+                signal[n - latency]
+            } else {
+                0.0
+            };
+
+            assert!((samples[0] - expected).abs() < 1e-6);
+            assert!((samples[1] + expected).abs() < 1e-6);
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use vst::channels::ChannelInfo;
+
+/// The plugins always expose 7.1 in SMPTE order. Stereo and 5.1 are prefixes of it,
+/// so they line up with the same channel names and weights.
+pub const CHANNELS_NUM: usize = 8;
+
+/// The channels the plugins declare to a VST2 host, which has no way to ask for
+/// another layout: stereo, or all of 7.1 with the `surround` feature. Hosts that
+/// pass fewer or more still get the channels processed, up to `CHANNELS_NUM`.
+#[cfg(not(feature = "surround"))]
+pub const DECLARED_CHANNELS_NUM: usize = 2;
+#[cfg(feature = "surround")]
+pub const DECLARED_CHANNELS_NUM: usize = CHANNELS_NUM;
+
+const NAMES: [(&str, &str); CHANNELS_NUM] = [
+    ("Left", "L"),
+    ("Right", "R"),
+    ("Center", "C"),
+    ("LFE", "LFE"),
+    ("Left Surround", "Ls"),
+    ("Right Surround", "Rs"),
+    ("Left Back", "Lb"),
+    ("Right Back", "Rb"),
+];
+
+// Channel weights of BS.1770-4 (Table 3): the surrounds, between ±60° and
+// ±120°, are boosted by 1.5 dB, the back surrounds beyond ±120° are not, and the
+// LFE channel is left out of the measurement.
+const WEIGHTS: [f32; CHANNELS_NUM] = [1.0, 1.0, 1.0, 0.0, 1.41, 1.41, 1.0, 1.0];
+
+/// Returns the loudness weight of the channel at the given index.
+pub fn weight(index: usize) -> f32 {
+    WEIGHTS.get(index).copied().unwrap_or(1.0)
+}
+
+/// Describes the channel at the given index to the host.
+pub fn channel_info(index: i32) -> ChannelInfo {
+    let (name, short_name) = NAMES
+        .get(index as usize)
+        .copied()
+        .unwrap_or(("Unused", "-"));
+
+    ChannelInfo::new(name.to_string(), Some(short_name.to_string()), true, None)
+}
@@ -0,0 +1,927 @@
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_void};
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use clap_sys::events::{
+    clap_event_header, clap_event_param_value, clap_input_events, clap_output_events,
+    CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_PARAM_VALUE,
+};
+use clap_sys::ext::audio_ports::{
+    clap_audio_port_info, clap_plugin_audio_ports, CLAP_AUDIO_PORT_IS_MAIN, CLAP_EXT_AUDIO_PORTS,
+    CLAP_PORT_STEREO,
+};
+use clap_sys::ext::gui::{
+    clap_gui_resize_hints, clap_plugin_gui, clap_window, CLAP_EXT_GUI, CLAP_WINDOW_API_X11,
+};
+use clap_sys::ext::latency::{clap_host_latency, clap_plugin_latency, CLAP_EXT_LATENCY};
+use clap_sys::ext::params::{
+    clap_param_info, clap_plugin_params, CLAP_EXT_PARAMS, CLAP_PARAM_IS_AUTOMATABLE,
+    CLAP_PARAM_IS_ENUM, CLAP_PARAM_IS_STEPPED,
+};
+use clap_sys::ext::state::{clap_plugin_state, CLAP_EXT_STATE};
+use clap_sys::factory::plugin_factory::{clap_plugin_factory, CLAP_PLUGIN_FACTORY_ID};
+use clap_sys::host::clap_host;
+use clap_sys::id::{clap_id, CLAP_INVALID_ID};
+use clap_sys::plugin::{clap_plugin, clap_plugin_descriptor};
+use clap_sys::plugin_features::{
+    CLAP_PLUGIN_FEATURE_AUDIO_EFFECT, CLAP_PLUGIN_FEATURE_LIMITER, CLAP_PLUGIN_FEATURE_MASTERING,
+};
+use clap_sys::process::{
+    clap_audio_buffer, clap_process, clap_process_status, CLAP_PROCESS_CONTINUE, CLAP_PROCESS_ERROR,
+};
+use clap_sys::stream::{clap_istream, clap_ostream};
+use vst::buffer::AudioBuffer;
+use vst::editor::Editor as VstEditor;
+use vst::plugin::{HostCallback, PluginParameters};
+
+pub use clap_sys::entry::clap_plugin_entry;
+pub use clap_sys::version::CLAP_VERSION;
+
+use crate::channels::CHANNELS_NUM;
+use crate::params::Params;
+use crate::wrapped::{copy_cstr, slug, Instance, Wrapped, WrappedPlugin};
+
+const VENDOR: &str = "youxkei";
+const URL: &str = "https://github.com/youxkei/jimtel";
+
+// The one plugin of the library, set up by `init`.
+static LIBRARY: OnceLock<Library> = OnceLock::new();
+
+struct Library {
+    descriptor: clap_plugin_descriptor,
+    new_wrapped: fn() -> Wrapped,
+
+    // What the descriptor points into.
+    _strings: Vec<CString>,
+    _features: Vec<*const c_char>,
+}
+
+// The descriptor only points into the strings kept alongside it, which are never
+// changed.
+unsafe impl Send for Library {}
+unsafe impl Sync for Library {}
+
+impl Library {
+    fn new<P: WrappedPlugin>() -> Self {
+        let info = P::new(HostCallback::default()).get_info();
+
+        let strings: Vec<CString> = [
+            format!("com.{}.{}", VENDOR, slug(&info.name)),
+            info.name,
+            VENDOR.to_string(),
+            URL.to_string(),
+            String::new(),
+            env!("CARGO_PKG_VERSION").to_string(),
+        ]
+        .into_iter()
+        .map(|string| CString::new(string).unwrap_or_default())
+        .collect();
+
+        let features = vec![
+            CLAP_PLUGIN_FEATURE_AUDIO_EFFECT.as_ptr(),
+            CLAP_PLUGIN_FEATURE_LIMITER.as_ptr(),
+            CLAP_PLUGIN_FEATURE_MASTERING.as_ptr(),
+            ptr::null(),
+        ];
+
+        let descriptor = clap_plugin_descriptor {
+            clap_version: CLAP_VERSION,
+            id: strings[0].as_ptr(),
+            name: strings[1].as_ptr(),
+            vendor: strings[2].as_ptr(),
+            url: strings[3].as_ptr(),
+            manual_url: strings[3].as_ptr(),
+            support_url: strings[3].as_ptr(),
+            version: strings[5].as_ptr(),
+            description: strings[4].as_ptr(),
+            features: features.as_ptr(),
+        };
+
+        Self {
+            descriptor,
+            new_wrapped: Wrapped::new::<P>,
+            _strings: strings,
+            _features: features,
+        }
+    }
+}
+
+// What the audio thread owns, behind one lock.
+struct Processor {
+    instance: Box<dyn Instance>,
+
+    // The sample rate the instance was last resumed at, 0 before the first
+    // activation.
+    sample_rate: f64,
+
+    // Channel pointers of the current sub-block.
+    inputs: Vec<*const f32>,
+    outputs: Vec<*mut f32>,
+}
+
+struct ClapPlugin {
+    plugin: clap_plugin,
+    host: *const clap_host,
+
+    processor: Mutex<Processor>,
+    params: Arc<dyn Params + Send + Sync>,
+    parameters: Arc<dyn PluginParameters>,
+    params_num: i32,
+
+    // The latency the host last read, and whether a restart to update it is pending.
+    latency_samples: AtomicU32,
+    restart_requested: AtomicBool,
+
+    // Only touched on the main thread.
+    editor: RefCell<Option<Box<dyn VstEditor>>>,
+}
+
+impl ClapPlugin {
+    unsafe fn from_raw<'a>(plugin: *const clap_plugin) -> &'a Self {
+        &*((*plugin).plugin_data as *const Self)
+    }
+
+    // Tells the host the latency changed, which it may only be told while the
+    // plugin is being activated.
+    unsafe fn notify_latency_changed(&self) {
+        let get_extension = match (*self.host).get_extension {
+            Some(get_extension) => get_extension,
+            None => return,
+        };
+
+        let latency =
+            get_extension(self.host, CLAP_EXT_LATENCY.as_ptr()) as *const clap_host_latency;
+        if let Some(changed) = latency.as_ref().and_then(|latency| latency.changed) {
+            changed(self.host);
+        }
+    }
+
+    fn has_param(&self, id: clap_id) -> bool {
+        id < self.params_num as u32
+    }
+
+    // Continuous params are 0..1 to the host, through the same (possibly skewed)
+    // curve as VST2 and VST3. Choices and toggles keep their steps, 0..last.
+    fn last_step(&self, index: i32) -> Option<f32> {
+        let choices_num = self.params.get_choices(index).len();
+
+        if choices_num > 0 {
+            Some((choices_num - 1) as f32)
+        } else if self.params.is_button(index) || self.params.is_checkbox(index) {
+            Some(1.0)
+        } else {
+            None
+        }
+    }
+
+    fn plain_value(&self, index: i32, value: f64) -> f32 {
+        match self.last_step(index) {
+            Some(_) => value as f32,
+            None => self.params.denormalize(index, value as f32),
+        }
+    }
+
+    fn host_value(&self, index: i32, value: f32) -> f64 {
+        match self.last_step(index) {
+            Some(_) => value as f64,
+            None => self.params.normalize(index, value) as f64,
+        }
+    }
+
+    fn set_param(&self, id: clap_id, value: f64) {
+        if !self.has_param(id) {
+            return;
+        }
+
+        let index = id as i32;
+        match self.last_step(index) {
+            Some(last) => self
+                .params
+                .set_value(index, (value as f32).round().max(0.0).min(last)),
+            None => self.parameters.set_parameter(index, value as f32),
+        }
+    }
+
+    // Applies a param value event and skips any other.
+    unsafe fn handle_event(&self, header: *const clap_event_header) {
+        if is_param_value(header) {
+            let event = &*(header as *const clap_event_param_value);
+            self.set_param(event.param_id, event.value);
+        }
+    }
+}
+
+/// The `init` of the entry point that `jimtel::clap_main!` exports.
+///
+/// # Safety
+///
+/// Called by the host only, as the CLAP spec describes.
+pub unsafe extern "C" fn init<P: WrappedPlugin>(_plugin_path: *const c_char) -> bool {
+    LIBRARY.get_or_init(Library::new::<P>);
+    true
+}
+
+/// The `deinit` of the entry point.
+///
+/// # Safety
+///
+/// Called by the host only.
+pub unsafe extern "C" fn deinit() {}
+
+/// The `get_factory` of the entry point.
+///
+/// # Safety
+///
+/// `factory_id` must be null or a null-terminated string.
+pub unsafe extern "C" fn get_factory(factory_id: *const c_char) -> *const c_void {
+    if !factory_id.is_null() && CStr::from_ptr(factory_id) == CLAP_PLUGIN_FACTORY_ID {
+        &FACTORY as *const clap_plugin_factory as *const c_void
+    } else {
+        ptr::null()
+    }
+}
+
+static FACTORY: clap_plugin_factory = clap_plugin_factory {
+    get_plugin_count: Some(get_plugin_count),
+    get_plugin_descriptor: Some(get_plugin_descriptor),
+    create_plugin: Some(create_plugin),
+};
+
+unsafe extern "C" fn get_plugin_count(_factory: *const clap_plugin_factory) -> u32 {
+    LIBRARY.get().map_or(0, |_| 1)
+}
+
+unsafe extern "C" fn get_plugin_descriptor(
+    _factory: *const clap_plugin_factory,
+    index: u32,
+) -> *const clap_plugin_descriptor {
+    match LIBRARY.get() {
+        Some(library) if index == 0 => &library.descriptor,
+        _ => ptr::null(),
+    }
+}
+
+unsafe extern "C" fn create_plugin(
+    _factory: *const clap_plugin_factory,
+    host: *const clap_host,
+    plugin_id: *const c_char,
+) -> *const clap_plugin {
+    let library = match LIBRARY.get() {
+        Some(library) => library,
+        None => return ptr::null(),
+    };
+
+    if plugin_id.is_null() || CStr::from_ptr(plugin_id) != CStr::from_ptr(library.descriptor.id) {
+        return ptr::null();
+    }
+
+    let wrapped = (library.new_wrapped)();
+    let latency_samples = wrapped.instance.latency_samples();
+
+    let plugin = Box::into_raw(Box::new(ClapPlugin {
+        plugin: clap_plugin {
+            desc: &library.descriptor,
+            plugin_data: ptr::null_mut(),
+            init: Some(plugin_init),
+            destroy: Some(plugin_destroy),
+            activate: Some(plugin_activate),
+            deactivate: Some(plugin_deactivate),
+            start_processing: Some(plugin_start_processing),
+            stop_processing: Some(plugin_stop_processing),
+            reset: Some(plugin_reset),
+            process: Some(plugin_process),
+            get_extension: Some(plugin_get_extension),
+            on_main_thread: Some(plugin_on_main_thread),
+        },
+        host,
+
+        processor: Mutex::new(Processor {
+            instance: wrapped.instance,
+            sample_rate: 0.0,
+            inputs: Vec::with_capacity(CHANNELS_NUM),
+            outputs: Vec::with_capacity(CHANNELS_NUM),
+        }),
+        params: wrapped.params,
+        parameters: wrapped.parameters,
+        params_num: wrapped.params_num,
+
+        latency_samples: AtomicU32::new(latency_samples),
+        restart_requested: AtomicBool::new(false),
+
+        editor: RefCell::new(None),
+    }));
+    (*plugin).plugin.plugin_data = plugin as *mut c_void;
+
+    &(*plugin).plugin
+}
+
+unsafe extern "C" fn plugin_init(_plugin: *const clap_plugin) -> bool {
+    true
+}
+
+unsafe extern "C" fn plugin_destroy(plugin: *const clap_plugin) {
+    let plugin = (*plugin).plugin_data as *mut ClapPlugin;
+
+    if let Some(mut editor) = (*plugin).editor.take() {
+        editor.close();
+    }
+
+    drop(Box::from_raw(plugin));
+}
+
+unsafe extern "C" fn plugin_activate(
+    plugin: *const clap_plugin,
+    sample_rate: f64,
+    _min_frames_count: u32,
+    max_frames_count: u32,
+) -> bool {
+    let plugin = ClapPlugin::from_raw(plugin);
+    let mut processor = plugin.processor.lock().unwrap();
+
+    // Resuming clears the measurements, so a restart that `process` asked for to
+    // pass on a latency change carries on where it was, unless the sample rate
+    // changed meanwhile.
+    let restarted_for_latency = plugin.restart_requested.swap(false, Ordering::Relaxed);
+    let resume = !restarted_for_latency || processor.sample_rate != sample_rate;
+    processor.sample_rate = sample_rate;
+
+    let instance = processor.instance.plugin();
+    instance.set_block_size(max_frames_count as i64);
+    if resume {
+        instance.set_sample_rate(sample_rate as f32);
+        instance.resume();
+    }
+
+    let latency_samples = processor.instance.latency_samples();
+    if plugin
+        .latency_samples
+        .swap(latency_samples, Ordering::Relaxed)
+        != latency_samples
+    {
+        plugin.notify_latency_changed();
+    }
+
+    true
+}
+
+unsafe extern "C" fn plugin_deactivate(plugin: *const clap_plugin) {
+    let plugin = ClapPlugin::from_raw(plugin);
+
+    plugin.processor.lock().unwrap().instance.plugin().suspend();
+}
+
+unsafe extern "C" fn plugin_start_processing(_plugin: *const clap_plugin) -> bool {
+    true
+}
+
+unsafe extern "C" fn plugin_stop_processing(_plugin: *const clap_plugin) {}
+
+unsafe extern "C" fn plugin_reset(plugin: *const clap_plugin) {
+    let plugin = ClapPlugin::from_raw(plugin);
+
+    // The plugins clear their state on resume.
+    plugin.processor.lock().unwrap().instance.plugin().resume();
+}
+
+unsafe extern "C" fn plugin_process(
+    plugin: *const clap_plugin,
+    process: *const clap_process,
+) -> clap_process_status {
+    if process.is_null() {
+        return CLAP_PROCESS_ERROR;
+    }
+
+    let plugin = ClapPlugin::from_raw(plugin);
+    let process = &*process;
+    let samples_num = process.frames_count as usize;
+    let mut processor = plugin.processor.lock().unwrap();
+    let processor = &mut *processor;
+
+    let (inputs, inputs_num) = port_channels(process.audio_inputs, process.audio_inputs_count);
+    let (outputs, outputs_num) = port_channels(process.audio_outputs, process.audio_outputs_count);
+
+    let events_num = input_events_size(process.in_events);
+    let mut event = 0;
+
+    // Param events come sorted by time. The block is split at each of them, on top
+    // of the plugins' own sub-blocks, so automation lands on its sample.
+    let mut start = 0;
+    while start < samples_num {
+        let mut end = samples_num;
+        while event < events_num {
+            let header = input_event(process.in_events, event);
+            if !is_param_value(header) {
+                event += 1;
+                continue;
+            }
+
+            let time = (*header).time as usize;
+            if time > start {
+                end = time.min(samples_num);
+                break;
+            }
+
+            plugin.handle_event(header);
+            event += 1;
+        }
+
+        processor.inputs.clear();
+        processor.outputs.clear();
+        for channel in 0..inputs_num {
+            processor
+                .inputs
+                .push((*inputs.add(channel) as *const f32).add(start));
+        }
+        for channel in 0..outputs_num {
+            processor.outputs.push((*outputs.add(channel)).add(start));
+        }
+
+        let mut buffer = AudioBuffer::from_raw(
+            inputs_num,
+            outputs_num,
+            processor.inputs.as_ptr(),
+            processor.outputs.as_mut_ptr(),
+            end - start,
+        );
+        processor.instance.plugin().process(&mut buffer);
+
+        start = end;
+    }
+
+    // Events at the very end of the block.
+    while event < events_num {
+        plugin.handle_event(input_event(process.in_events, event));
+        event += 1;
+    }
+
+    // The latency may only change while deactivated, so ask the host to restart
+    // the plugin, which reads it again.
+    let latency_samples = processor.instance.latency_samples();
+    if latency_samples != plugin.latency_samples.load(Ordering::Relaxed)
+        && !plugin.restart_requested.swap(true, Ordering::Relaxed)
+    {
+        if let Some(request_restart) = (*plugin.host).request_restart {
+            request_restart(plugin.host);
+        }
+    }
+
+    CLAP_PROCESS_CONTINUE
+}
+
+unsafe extern "C" fn plugin_get_extension(
+    _plugin: *const clap_plugin,
+    id: *const c_char,
+) -> *const c_void {
+    if id.is_null() {
+        return ptr::null();
+    }
+
+    let id = CStr::from_ptr(id);
+    if id == CLAP_EXT_AUDIO_PORTS {
+        &AUDIO_PORTS as *const clap_plugin_audio_ports as *const c_void
+    } else if id == CLAP_EXT_PARAMS {
+        &PARAMS as *const clap_plugin_params as *const c_void
+    } else if id == CLAP_EXT_LATENCY {
+        &LATENCY as *const clap_plugin_latency as *const c_void
+    } else if id == CLAP_EXT_STATE {
+        &STATE as *const clap_plugin_state as *const c_void
+    } else if id == CLAP_EXT_GUI {
+        &GUI as *const clap_plugin_gui as *const c_void
+    } else {
+        ptr::null()
+    }
+}
+
+unsafe extern "C" fn plugin_on_main_thread(_plugin: *const clap_plugin) {}
+
+static AUDIO_PORTS: clap_plugin_audio_ports = clap_plugin_audio_ports {
+    count: Some(audio_ports_count),
+    get: Some(audio_ports_get),
+};
+
+unsafe extern "C" fn audio_ports_count(_plugin: *const clap_plugin, _is_input: bool) -> u32 {
+    1
+}
+
+// One stereo port each way; the plugins weight the channels as the front of 7.1.
+unsafe extern "C" fn audio_ports_get(
+    _plugin: *const clap_plugin,
+    index: u32,
+    is_input: bool,
+    info: *mut clap_audio_port_info,
+) -> bool {
+    if index != 0 || info.is_null() {
+        return false;
+    }
+
+    let info = &mut *info;
+    info.id = 0;
+    copy_cstr(&mut info.name, if is_input { "Input" } else { "Output" });
+    info.flags = CLAP_AUDIO_PORT_IS_MAIN;
+    info.channel_count = 2;
+    info.port_type = CLAP_PORT_STEREO.as_ptr();
+    info.in_place_pair = CLAP_INVALID_ID;
+
+    true
+}
+
+static PARAMS: clap_plugin_params = clap_plugin_params {
+    count: Some(params_count),
+    get_info: Some(params_get_info),
+    get_value: Some(params_get_value),
+    value_to_text: Some(params_value_to_text),
+    text_to_value: Some(params_text_to_value),
+    flush: Some(params_flush),
+};
+
+unsafe extern "C" fn params_count(plugin: *const clap_plugin) -> u32 {
+    ClapPlugin::from_raw(plugin).params_num as u32
+}
+
+unsafe extern "C" fn params_get_info(
+    plugin: *const clap_plugin,
+    param_index: u32,
+    param_info: *mut clap_param_info,
+) -> bool {
+    let plugin = ClapPlugin::from_raw(plugin);
+    if !plugin.has_param(param_index) || param_info.is_null() {
+        return false;
+    }
+
+    let index = param_index as i32;
+    let info = &mut *param_info;
+    let params = &plugin.params;
+
+    info.id = param_index;
+    info.flags = match plugin.last_step(index) {
+        Some(_) if !params.get_choices(index).is_empty() => {
+            CLAP_PARAM_IS_AUTOMATABLE | CLAP_PARAM_IS_STEPPED | CLAP_PARAM_IS_ENUM
+        }
+        Some(_) => CLAP_PARAM_IS_AUTOMATABLE | CLAP_PARAM_IS_STEPPED,
+        None => CLAP_PARAM_IS_AUTOMATABLE,
+    };
+    info.cookie = ptr::null_mut();
+    copy_cstr(&mut info.name, &params.get_name(index));
+    copy_cstr(&mut info.module, &params.get_section(index));
+    info.min_value = 0.0;
+    info.max_value = plugin.last_step(index).unwrap_or(1.0) as f64;
+    info.default_value = plugin.host_value(index, params.get_default(index));
+
+    true
+}
+
+unsafe extern "C" fn params_get_value(
+    plugin: *const clap_plugin,
+    param_id: clap_id,
+    out_value: *mut f64,
+) -> bool {
+    let plugin = ClapPlugin::from_raw(plugin);
+    if !plugin.has_param(param_id) || out_value.is_null() {
+        return false;
+    }
+
+    let index = param_id as i32;
+    *out_value = plugin.host_value(index, plugin.params.get_value(index));
+
+    true
+}
+
+unsafe extern "C" fn params_value_to_text(
+    plugin: *const clap_plugin,
+    param_id: clap_id,
+    value: f64,
+    out_buffer: *mut c_char,
+    out_buffer_capacity: u32,
+) -> bool {
+    let plugin = ClapPlugin::from_raw(plugin);
+    if !plugin.has_param(param_id) || out_buffer.is_null() || out_buffer_capacity == 0 {
+        return false;
+    }
+
+    let index = param_id as i32;
+    let text = plugin
+        .params
+        .value_to_text(index, plugin.plain_value(index, value));
+    copy_cstr(
+        std::slice::from_raw_parts_mut(out_buffer, out_buffer_capacity as usize),
+        &text,
+    );
+
+    true
+}
+
+unsafe extern "C" fn params_text_to_value(
+    plugin: *const clap_plugin,
+    param_id: clap_id,
+    param_value_text: *const c_char,
+    out_value: *mut f64,
+) -> bool {
+    let plugin = ClapPlugin::from_raw(plugin);
+    if !plugin.has_param(param_id) || param_value_text.is_null() || out_value.is_null() {
+        return false;
+    }
+
+    let index = param_id as i32;
+    let text = CStr::from_ptr(param_value_text).to_string_lossy();
+    match plugin.params.text_to_value(index, &text) {
+        Some(value) => {
+            *out_value = plugin.host_value(index, value);
+            true
+        }
+
+        None => false,
+    }
+}
+
+// Param events outside of `process`, while the plugin is not processing.
+unsafe extern "C" fn params_flush(
+    plugin: *const clap_plugin,
+    in_events: *const clap_input_events,
+    _out_events: *const clap_output_events,
+) {
+    let plugin = ClapPlugin::from_raw(plugin);
+
+    for event in 0..input_events_size(in_events) {
+        plugin.handle_event(input_event(in_events, event));
+    }
+}
+
+static LATENCY: clap_plugin_latency = clap_plugin_latency {
+    get: Some(latency_get),
+};
+
+unsafe extern "C" fn latency_get(plugin: *const clap_plugin) -> u32 {
+    ClapPlugin::from_raw(plugin)
+        .latency_samples
+        .load(Ordering::Relaxed)
+}
+
+static STATE: clap_plugin_state = clap_plugin_state {
+    save: Some(state_save),
+    load: Some(state_load),
+};
+
+// The state is the bank data of the VST2 build, so sessions can move between the
+// formats.
+unsafe extern "C" fn state_save(plugin: *const clap_plugin, stream: *const clap_ostream) -> bool {
+    let plugin = ClapPlugin::from_raw(plugin);
+    let write = match stream.as_ref().and_then(|stream| stream.write) {
+        Some(write) => write,
+        None => return false,
+    };
+
+    let data = plugin.parameters.get_bank_data();
+    let mut written = 0;
+    while written < data.len() {
+        let result = write(
+            stream,
+            data[written..].as_ptr() as *const c_void,
+            (data.len() - written) as u64,
+        );
+        if result <= 0 {
+            return false;
+        }
+
+        written += result as usize;
+    }
+
+    true
+}
+
+unsafe extern "C" fn state_load(plugin: *const clap_plugin, stream: *const clap_istream) -> bool {
+    let plugin = ClapPlugin::from_raw(plugin);
+    let read = match stream.as_ref().and_then(|stream| stream.read) {
+        Some(read) => read,
+        None => return false,
+    };
+
+    let mut data = Vec::new();
+    let mut chunk = [0u8; 4096];
+    loop {
+        let result = read(
+            stream,
+            chunk.as_mut_ptr() as *mut c_void,
+            chunk.len() as u64,
+        );
+        if result < 0 {
+            return false;
+        }
+        if result == 0 {
+            break;
+        }
+
+        data.extend_from_slice(&chunk[..result as usize]);
+    }
+
+    plugin.parameters.load_bank_data(&data);
+
+    true
+}
+
+static GUI: clap_plugin_gui = clap_plugin_gui {
+    is_api_supported: Some(gui_is_api_supported),
+    get_preferred_api: Some(gui_get_preferred_api),
+    create: Some(gui_create),
+    destroy: Some(gui_destroy),
+    set_scale: Some(gui_set_scale),
+    get_size: Some(gui_get_size),
+    can_resize: Some(gui_can_resize),
+    get_resize_hints: Some(gui_get_resize_hints),
+    adjust_size: Some(gui_adjust_size),
+    set_size: Some(gui_set_size),
+    set_parent: Some(gui_set_parent),
+    set_transient: Some(gui_set_transient),
+    suggest_title: Some(gui_suggest_title),
+    show: Some(gui_show),
+    hide: Some(gui_hide),
+};
+
+// The editor is embedded into an X11 window of the host; floating windows are
+// not offered.
+unsafe extern "C" fn gui_is_api_supported(
+    _plugin: *const clap_plugin,
+    api: *const c_char,
+    is_floating: bool,
+) -> bool {
+    cfg!(target_os = "linux")
+        && !is_floating
+        && !api.is_null()
+        && CStr::from_ptr(api) == CLAP_WINDOW_API_X11
+}
+
+unsafe extern "C" fn gui_get_preferred_api(
+    _plugin: *const clap_plugin,
+    api: *mut *const c_char,
+    is_floating: *mut bool,
+) -> bool {
+    if !cfg!(target_os = "linux") || api.is_null() || is_floating.is_null() {
+        return false;
+    }
+
+    *api = CLAP_WINDOW_API_X11.as_ptr();
+    *is_floating = false;
+
+    true
+}
+
+unsafe extern "C" fn gui_create(
+    plugin: *const clap_plugin,
+    api: *const c_char,
+    is_floating: bool,
+) -> bool {
+    if !gui_is_api_supported(plugin, api, is_floating) {
+        return false;
+    }
+
+    let plugin = ClapPlugin::from_raw(plugin);
+    let editor = plugin
+        .processor
+        .lock()
+        .unwrap()
+        .instance
+        .plugin()
+        .get_editor();
+
+    let created = editor.is_some();
+    plugin.editor.replace(editor);
+
+    created
+}
+
+unsafe extern "C" fn gui_destroy(plugin: *const clap_plugin) {
+    let plugin = ClapPlugin::from_raw(plugin);
+
+    if let Some(mut editor) = plugin.editor.take() {
+        editor.close();
+    }
+}
+
+unsafe extern "C" fn gui_set_scale(_plugin: *const clap_plugin, _scale: f64) -> bool {
+    false
+}
+
+unsafe extern "C" fn gui_get_size(
+    plugin: *const clap_plugin,
+    width: *mut u32,
+    height: *mut u32,
+) -> bool {
+    let plugin = ClapPlugin::from_raw(plugin);
+    if width.is_null() || height.is_null() {
+        return false;
+    }
+
+    match plugin.editor.borrow().as_ref() {
+        Some(editor) => {
+            let (editor_width, editor_height) = editor.size();
+            *width = editor_width as u32;
+            *height = editor_height as u32;
+            true
+        }
+
+        None => false,
+    }
+}
+
+unsafe extern "C" fn gui_can_resize(_plugin: *const clap_plugin) -> bool {
+    false
+}
+
+unsafe extern "C" fn gui_get_resize_hints(
+    _plugin: *const clap_plugin,
+    _hints: *mut clap_gui_resize_hints,
+) -> bool {
+    false
+}
+
+unsafe extern "C" fn gui_adjust_size(
+    _plugin: *const clap_plugin,
+    _width: *mut u32,
+    _height: *mut u32,
+) -> bool {
+    false
+}
+
+unsafe extern "C" fn gui_set_size(_plugin: *const clap_plugin, _width: u32, _height: u32) -> bool {
+    false
+}
+
+unsafe extern "C" fn gui_set_parent(
+    plugin: *const clap_plugin,
+    window: *const clap_window,
+) -> bool {
+    let plugin = ClapPlugin::from_raw(plugin);
+    if window.is_null() {
+        return false;
+    }
+
+    let parent = (*window).specific.x11 as *mut c_void;
+    match plugin.editor.borrow_mut().as_mut() {
+        Some(editor) => editor.open(parent),
+        None => false,
+    }
+}
+
+unsafe extern "C" fn gui_set_transient(
+    _plugin: *const clap_plugin,
+    _window: *const clap_window,
+) -> bool {
+    false
+}
+
+unsafe extern "C" fn gui_suggest_title(_plugin: *const clap_plugin, _title: *const c_char) {}
+
+unsafe extern "C" fn gui_show(_plugin: *const clap_plugin) -> bool {
+    true
+}
+
+unsafe extern "C" fn gui_hide(_plugin: *const clap_plugin) -> bool {
+    true
+}
+
+// The channel pointers of the first port, and how many of them the plugins take.
+unsafe fn port_channels(ports: *const clap_audio_buffer, ports_num: u32) -> (*mut *mut f32, usize) {
+    if ports.is_null() || ports_num < 1 || (*ports).data32.is_null() {
+        return (ptr::null_mut(), 0);
+    }
+
+    let port = &*ports;
+    (port.data32, (port.channel_count as usize).min(CHANNELS_NUM))
+}
+
+unsafe fn input_events_size(events: *const clap_input_events) -> u32 {
+    match events.as_ref().and_then(|events| events.size) {
+        Some(size) => size(events),
+        None => 0,
+    }
+}
+
+unsafe fn input_event(events: *const clap_input_events, index: u32) -> *const clap_event_header {
+    match (*events).get {
+        Some(get) => get(events, index),
+        None => ptr::null(),
+    }
+}
+
+unsafe fn is_param_value(header: *const clap_event_header) -> bool {
+    !header.is_null()
+        && (*header).space_id == CLAP_CORE_EVENT_SPACE_ID
+        && (*header).type_ == CLAP_EVENT_PARAM_VALUE
+}
+
+/// Exports the plugin as CLAP next to its VST2 entry point, from the same library.
+#[macro_export]
+macro_rules! clap_main {
+    ($plugin:ty) => {
+        #[no_mangle]
+        #[allow(non_upper_case_globals)]
+        pub static clap_entry: $crate::clap::clap_plugin_entry = $crate::clap::clap_plugin_entry {
+            clap_version: $crate::clap::CLAP_VERSION,
+            init: Some($crate::clap::init::<$plugin>),
+            deinit: Some($crate::clap::deinit),
+            get_factory: Some($crate::clap::get_factory),
+        };
+    };
+}
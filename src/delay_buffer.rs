@@ -1,28 +1,42 @@
 pub struct DelayBuffer {
+    channels_num: usize,
     delay: usize,
-    buffer: Vec<(f32, f32)>,
+    // One frame of channels_num samples per delayed sample, frames in a ring.
+    buffer: Vec<f32>,
     current_index: usize,
 }
 
 impl DelayBuffer {
-    pub fn new(delay: usize) -> Self {
+    pub fn new(channels_num: usize, delay: usize) -> Self {
         Self {
+            channels_num,
             delay,
-            buffer: vec![(0.0, 0.0); delay + 1],
+            buffer: vec![0.0; (delay + 1) * channels_num],
             current_index: 0,
         }
     }
 
+    /// Takes one sample per channel and returns the ones from `delay` samples ago.
     #[inline(always)]
-    pub fn add(&mut self, current_left_value: f32, current_right_value: f32) -> (f32, f32) {
-        self.buffer[self.current_index] = (current_left_value, current_right_value);
+    pub fn add(&mut self, current_values: &[f32]) -> &[f32] {
+        let channels_num = self.channels_num;
+
+        let frame = self.current_index * channels_num;
+        self.buffer[frame..frame + channels_num].copy_from_slice(current_values);
 
         self.current_index += 1;
-        if self.current_index >= self.buffer.len() {
+        if self.current_index > self.delay {
             self.current_index = 0;
         }
 
-        self.buffer[self.current_index]
+        let frame = self.current_index * channels_num;
+        &self.buffer[frame..frame + channels_num]
+    }
+
+    /// Silences the delayed samples in place.
+    pub fn reset(&mut self) {
+        self.buffer.iter_mut().for_each(|value| *value = 0.0);
+        self.current_index = 0;
     }
 
     #[inline(always)]
@@ -30,7 +44,7 @@ impl DelayBuffer {
         if delay != self.delay {
             self.current_index = 0;
             self.delay = delay;
-            self.buffer = vec![(0.0, 0.0); delay + 1];
+            self.buffer = vec![0.0; (delay + 1) * self.channels_num];
         }
     }
 }
@@ -41,22 +55,43 @@ mod tests {
 
     #[test]
     fn no_delay() {
-        let mut buffer = DelayBuffer::new(0);
+        let mut buffer = DelayBuffer::new(2, 0);
 
-        assert_eq!(buffer.add(1.0, 2.0), (1.0, 2.0));
-        assert_eq!(buffer.add(3.0, 4.0), (3.0, 4.0));
+        assert_eq!(buffer.add(&[1.0, 2.0]), [1.0, 2.0]);
+        assert_eq!(buffer.add(&[3.0, 4.0]), [3.0, 4.0]);
     }
 
     #[test]
     fn some_delay() {
-        let mut buffer = DelayBuffer::new(3);
-
-        assert_eq!(buffer.add(1.0, 2.0), (0.0, 0.0));
-        assert_eq!(buffer.add(3.0, 4.0), (0.0, 0.0));
-        assert_eq!(buffer.add(5.0, 6.0), (0.0, 0.0));
-        assert_eq!(buffer.add(7.0, 8.0), (1.0, 2.0));
-        assert_eq!(buffer.add(8.0, 9.0), (3.0, 4.0));
-        assert_eq!(buffer.add(10.0, 11.0), (5.0, 6.0));
-        assert_eq!(buffer.add(12.0, 13.0), (7.0, 8.0));
+        let mut buffer = DelayBuffer::new(2, 3);
+
+        assert_eq!(buffer.add(&[1.0, 2.0]), [0.0, 0.0]);
+        assert_eq!(buffer.add(&[3.0, 4.0]), [0.0, 0.0]);
+        assert_eq!(buffer.add(&[5.0, 6.0]), [0.0, 0.0]);
+        assert_eq!(buffer.add(&[7.0, 8.0]), [1.0, 2.0]);
+        assert_eq!(buffer.add(&[8.0, 9.0]), [3.0, 4.0]);
+        assert_eq!(buffer.add(&[10.0, 11.0]), [5.0, 6.0]);
+        assert_eq!(buffer.add(&[12.0, 13.0]), [7.0, 8.0]);
+    }
+
+    #[test]
+    fn reset() {
+        let mut buffer = DelayBuffer::new(2, 1);
+        buffer.add(&[1.0, 2.0]);
+
+        buffer.reset();
+        assert_eq!(buffer.add(&[3.0, 4.0]), [0.0, 0.0]);
+        assert_eq!(buffer.add(&[5.0, 6.0]), [3.0, 4.0]);
+    }
+
+    #[test]
+    fn surround_delay() {
+        let mut buffer = DelayBuffer::new(6, 1);
+
+        assert_eq!(buffer.add(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]), [0.0; 6]);
+        assert_eq!(
+            buffer.add(&[7.0, 8.0, 9.0, 10.0, 11.0, 12.0]),
+            [1.0, 2.0, 3.0, 4.0, 5.0, 6.0]
+        );
     }
 }
@@ -44,6 +44,9 @@ struct State<Params> {
 
     // For each meter group, the meter index currently selected for display.
     meter_selection: HashMap<String, i32>,
+
+    // Text of the param values being typed in, by param index.
+    value_edits: HashMap<i32, String>,
 }
 
 impl<Params: VstParams> State<Params> {
@@ -66,6 +69,7 @@ impl<Params: VstParams> State<Params> {
             last_sample: None,
             timeline_offset: 0.0,
             meter_selection,
+            value_edits: HashMap::new(),
         }
     }
 }
@@ -77,6 +81,7 @@ pub struct Editor<Params> {
 
     opened: bool,
     params: Arc<Params>,
+    on_idle: Option<Box<dyn FnMut() + Send>>,
 }
 
 impl<Params> Editor<Params> {
@@ -87,8 +92,15 @@ impl<Params> Editor<Params> {
             height,
             params,
             opened: false,
+            on_idle: None,
         }
     }
+
+    /// Runs `on_idle` whenever the host idles the editor, on its UI thread.
+    pub fn on_idle(mut self, on_idle: impl FnMut() + Send + 'static) -> Self {
+        self.on_idle = Some(Box::new(on_idle));
+        self
+    }
 }
 
 // Renders the meters as time-series plots, one plot per unit, with a shared
@@ -164,7 +176,7 @@ fn render_meters<Params: VstParams>(ui: &mut egui::Ui, state: &mut State<Params>
             if mgroup.is_empty() {
                 ui.horizontal(|ui| {
                     ui.colored_label(color, params.get_meter_name(index));
-                    ui.label(format!("{} {}", params.get_meter_value_text(index), unit));
+                    ui.label(params.get_meter_value_text(index));
                 });
                 displayed.push((index, color));
             } else if !shown_groups.contains(&mgroup) {
@@ -191,7 +203,7 @@ fn render_meters<Params: VstParams>(ui: &mut egui::Ui, state: &mut State<Params>
                             .to_string();
                         ui.selectable_value(&mut selected, v, label);
                     }
-                    ui.label(format!("{} {}", params.get_meter_value_text(selected), unit));
+                    ui.label(params.get_meter_value_text(selected));
                 });
 
                 state.meter_selection.insert(mgroup, selected);
@@ -259,6 +271,120 @@ fn render_meters<Params: VstParams>(ui: &mut egui::Ui, state: &mut State<Params>
     state.timeline_offset = scroll.state.offset.x;
 }
 
+// Renders the params of one section as rows of a name and a control.
+fn render_param_grid<Params: VstParams>(
+    ui: &mut egui::Ui,
+    state: &mut State<Params>,
+    section: &str,
+    indices: &[i32],
+) {
+    Grid::new(("param grid", section)).show(ui, |ui| {
+        for &index in indices {
+            let value = state.params.get_value(index);
+            let choices = state.params.get_choices(index);
+
+            if state.params.is_button(index) {
+                if ui.button(state.params.get_name(index)).clicked() {
+                    if value < 0.5 {
+                        state.params.set_value(index, 1.0)
+                    } else {
+                        state.params.set_value(index, 0.0)
+                    }
+                }
+            } else if state.params.is_checkbox(index) {
+                let mut checked = value > 0.5;
+                if ui
+                    .checkbox(&mut checked, state.params.get_name(index))
+                    .changed()
+                {
+                    if checked {
+                        state.params.set_value(index, 1.0);
+                    } else {
+                        state.params.set_value(index, 0.0);
+                    }
+                }
+            } else if !choices.is_empty() {
+                ui.label(state.params.get_name(index));
+
+                let mut selected = value.max(0.0).round() as usize;
+                egui::ComboBox::from_id_source(("choice", index))
+                    .selected_text(state.params.get_value_text(index))
+                    .show_ui(ui, |ui| {
+                        for (choice, name) in choices.iter().enumerate() {
+                            ui.selectable_value(&mut selected, choice, name.as_str());
+                        }
+                    });
+
+                if selected as f32 != value {
+                    state.params.set_value(index, selected as f32);
+                }
+            } else {
+                ui.label(state.params.get_name(index));
+
+                // The slider moves through the same (possibly skewed) 0..1 range as the
+                // host; the value next to it reads and parses like the host's text.
+                let mut normalized = state.params.normalize(index, value);
+                let slider_response =
+                    ui.add(egui::Slider::new(&mut normalized, 0.0..=1.0).show_value(false));
+
+                let mut text = match state.value_edits.get(&index) {
+                    Some(text) => text.clone(),
+                    None => state.params.get_value_text(index),
+                };
+                let text_response =
+                    ui.add(egui::TextEdit::singleline(&mut text).desired_width(120.0));
+
+                // Double-click resets to the declared default.
+                if slider_response.double_clicked() {
+                    state
+                        .params
+                        .set_value(index, state.params.get_default(index));
+                } else if slider_response.changed() {
+                    let value = state.params.denormalize(index, normalized);
+                    state.params.set_value(index, value);
+                }
+
+                // Keep the typed text while editing; apply it once done, falling back
+                // to the value if it does not parse.
+                if text_response.has_focus() {
+                    state.value_edits.insert(index, text);
+                } else if state.value_edits.remove(&index).is_some() {
+                    state.params.set_value_text(index, &text);
+                }
+            }
+
+            ui.end_row();
+        }
+    });
+}
+
+// Renders the params section by section, in the order the sections are first
+// declared. Params without a section form a plain grid; the others go under a
+// collapsible heading, open to begin with.
+fn render_params<Params: VstParams>(ui: &mut egui::Ui, state: &mut State<Params>) {
+    let mut sections: Vec<(String, Vec<i32>)> = Vec::new();
+    for index in Params::index_range() {
+        let section = state.params.get_section(index);
+        match sections
+            .iter_mut()
+            .find(|(existing, _)| *existing == section)
+        {
+            Some((_, indices)) => indices.push(index),
+            None => sections.push((section, vec![index])),
+        }
+    }
+
+    for (section, indices) in sections {
+        if section.is_empty() {
+            render_param_grid(ui, state, &section, &indices);
+        } else {
+            egui::CollapsingHeader::new(section.as_str())
+                .default_open(true)
+                .show(ui, |ui| render_param_grid(ui, state, &section, &indices));
+        }
+    }
+}
+
 impl<Params: 'static + VstParams + Send + Sync> VstEditor for Editor<Params> {
     fn size(&self) -> (i32, i32) {
         (self.width as i32, self.height as i32)
@@ -342,51 +468,7 @@ impl<Params: 'static + VstParams + Send + Sync> VstEditor for Editor<Params> {
 
                 CentralPanel::default().show(&ctx, |ui| {
                     ScrollArea::vertical().show(ui, |ui| {
-                        Grid::new("root grid").show(ui, |ui| {
-                            for index in Params::index_range() {
-                                let mut value = state.params.get_value(index);
-
-                                if state.params.is_button(index) {
-                                    if ui.button(state.params.get_name(index)).clicked() {
-                                        if value < 0.5 {
-                                            state.params.set_value(index, 1.0)
-                                        } else {
-                                            state.params.set_value(index, 0.0)
-                                        }
-                                    }
-                                } else if state.params.is_checkbox(index) {
-                                    let mut checked = value > 0.5;
-                                    if ui
-                                        .checkbox(&mut checked, state.params.get_name(index))
-                                        .changed()
-                                    {
-                                        if checked {
-                                            state.params.set_value(index, 1.0);
-                                        } else {
-                                            state.params.set_value(index, 0.0);
-                                        }
-                                    }
-                                } else {
-                                    ui.label(state.params.get_name(index));
-
-                                    if ui
-                                        .add(
-                                            egui::Slider::new(
-                                                &mut value,
-                                                state.params.get_range(index),
-                                            )
-                                            .clamp_to_range(true)
-                                            .suffix(state.params.get_unit(index)),
-                                        )
-                                        .changed()
-                                    {
-                                        state.params.set_value(index, value);
-                                    }
-                                }
-
-                                ui.end_row();
-                            }
-                        });
+                        render_params(ui, state);
 
                         render_meters(ui, state);
                     });
@@ -401,6 +483,12 @@ impl<Params: 'static + VstParams + Send + Sync> VstEditor for Editor<Params> {
         self.opened
     }
 
+    fn idle(&mut self) {
+        if let Some(on_idle) = &mut self.on_idle {
+            on_idle();
+        }
+    }
+
     fn close(&mut self) {
         self.opened = false;
     }
@@ -0,0 +1,68 @@
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+
+use vst::host::Host;
+use vst::plugin::HostCallback;
+
+// audioMasterIOChanged: asks the host to re-read the AEffect, including its
+// initialDelay. The vst crate does not wrap it.
+const AUDIO_MASTER_IO_CHANGED: i32 = 13;
+
+/// Passes latency changes on to a VST2 host. `process` only records them, since
+/// hosts expect audioMasterIOChanged and updateDisplay on the UI thread; `report`
+/// sends them from there (the editor's idle, or `resume`).
+pub struct LatencyReporter {
+    host: HostCallback,
+    latency_samples: AtomicI32,
+    changed: AtomicBool,
+}
+
+// The host callback is only called from `report`, on the UI thread.
+unsafe impl Sync for LatencyReporter {}
+
+impl LatencyReporter {
+    /// `latency_samples` is what the host reads from `Info::initial_delay`.
+    pub fn new(host: HostCallback, latency_samples: i32) -> Self {
+        Self {
+            host,
+            latency_samples: AtomicI32::new(latency_samples),
+            changed: AtomicBool::new(false),
+        }
+    }
+
+    /// Records the current latency, on any thread.
+    pub fn set(&self, latency_samples: i32) {
+        if self
+            .latency_samples
+            .swap(latency_samples, Ordering::Relaxed)
+            != latency_samples
+        {
+            self.changed.store(true, Ordering::Release);
+        }
+    }
+
+    /// Updates the latency the host sees, if it changed since the last report, and
+    /// asks it to recompensate. Call on the UI thread only.
+    pub fn report(&self) {
+        if self.changed.swap(false, Ordering::Acquire) {
+            report_latency(&self.host, self.latency_samples.load(Ordering::Relaxed));
+        }
+    }
+}
+
+fn report_latency(host: &HostCallback, latency_samples: i32) {
+    let effect = host.raw_effect();
+    if effect.is_null() {
+        return;
+    }
+
+    unsafe {
+        (*effect).initialDelay = latency_samples;
+    }
+
+    if let Some(callback) = host.raw_callback() {
+        callback(effect, AUDIO_MASTER_IO_CHANGED, 0, 0, ptr::null_mut(), 0.0);
+    }
+
+    host.update_display();
+}
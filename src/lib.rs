@@ -1,7 +1,23 @@
+pub mod brickwall;
+pub mod channels;
+#[cfg(feature = "clap")]
+pub mod clap;
 pub mod delay_buffer;
 pub mod editor;
 pub mod envelope;
+pub mod latency;
 pub mod loudness;
+#[cfg(feature = "lv2")]
+pub mod lv2;
+pub mod normalize;
 pub mod params;
+pub mod smoother;
+pub mod sub_blocks;
 pub mod sum_buffer;
+#[cfg(any(test, feature = "test-host"))]
+pub mod test_host;
+pub mod true_peak;
+#[cfg(feature = "vst3")]
+pub mod vst3;
 pub mod window_handle;
+pub mod wrapped;
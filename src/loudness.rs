@@ -1,3 +1,4 @@
+use crate::channels;
 use crate::sum_buffer::SumBuffer;
 use std::f32;
 
@@ -5,8 +6,7 @@ pub struct Loudness {
     samples_num_per_loudness_window: usize,
     samples_num_per_power_window: usize,
 
-    left_prefilter: Prefilter,
-    right_prefilter: Prefilter,
+    prefilters: ChannelPrefilters,
 
     loudness_power_buffer: SumBuffer,
     power_buffer: SumBuffer,
@@ -15,6 +15,7 @@ pub struct Loudness {
 impl Loudness {
     pub fn new(
         sample_rate_hz: f32,
+        channels_num: usize,
         samples_num_per_loudness_window: usize,
         samples_num_per_power_window: usize,
     ) -> Loudness {
@@ -22,18 +23,16 @@ impl Loudness {
             samples_num_per_loudness_window,
             samples_num_per_power_window,
 
-            left_prefilter: Prefilter::new(sample_rate_hz),
-            right_prefilter: Prefilter::new(sample_rate_hz),
+            prefilters: ChannelPrefilters::new(sample_rate_hz, channels_num),
 
             loudness_power_buffer: SumBuffer::new(samples_num_per_loudness_window),
             power_buffer: SumBuffer::new(samples_num_per_power_window),
         }
     }
 
-    pub fn add_samples(&mut self, left_sample: f32, right_sample: f32) -> (f32, f32) {
-        let left_sample = self.left_prefilter.apply(left_sample);
-        let right_sample = self.right_prefilter.apply(right_sample);
-        let current_power = left_sample * left_sample + right_sample * right_sample;
+    /// Takes one sample per channel.
+    pub fn add_samples(&mut self, samples: &[f32]) -> (f32, f32) {
+        let current_power = self.prefilters.power(samples);
 
         let loudness_power_sum = self.loudness_power_buffer.add(current_power);
         let power_sum = self.power_buffer.add(current_power);
@@ -61,6 +60,319 @@ impl Loudness {
     }
 }
 
+// Gated measurements are taken on blocks advancing in 100 ms steps. Blocks quieter
+// than the absolute gate never count.
+const STEP_SECONDS: f32 = 0.1;
+const ABSOLUTE_GATE_LKFS: f32 = -70.0;
+
+// Integrated loudness uses 400 ms blocks overlapping by 75 % (BS.1770-4 section 2.8).
+const INTEGRATED_STEPS_NUM_PER_BLOCK: usize = 4;
+const INTEGRATED_RELATIVE_GATE_LU: f32 = -10.0;
+
+// Loudness range uses 3 s short-term blocks and measures the spread between the
+// 10th and the 95th percentiles of the gated ones (EBU Tech 3342).
+const RANGE_STEPS_NUM_PER_BLOCK: usize = 30;
+const RANGE_RELATIVE_GATE_LU: f32 = -20.0;
+const RANGE_LOW_PERCENTILE: f64 = 0.10;
+const RANGE_HIGH_PERCENTILE: f64 = 0.95;
+
+// Block loudness is accumulated into a histogram instead of being kept one by one,
+// so memory stays bounded however long the measurement runs.
+const HISTOGRAM_MIN_LKFS: f32 = ABSOLUTE_GATE_LKFS;
+const HISTOGRAM_MAX_LKFS: f32 = 20.0;
+const HISTOGRAM_BINS_NUM_PER_LU: f32 = 100.0;
+
+pub struct IntegratedLoudness {
+    blocks: GatingBlocks,
+    histogram: Histogram,
+}
+
+impl IntegratedLoudness {
+    pub fn new(sample_rate_hz: f32, channels_num: usize) -> IntegratedLoudness {
+        IntegratedLoudness {
+            blocks: GatingBlocks::new(sample_rate_hz, channels_num, INTEGRATED_STEPS_NUM_PER_BLOCK),
+            histogram: Histogram::new(),
+        }
+    }
+
+    /// Takes one sample per channel.
+    pub fn add_samples(&mut self, samples: &[f32]) {
+        if let Some(block_power) = self.blocks.add_samples(samples) {
+            self.histogram.add(block_power);
+        }
+    }
+
+    /// Returns the gated mean power, or 0 if no block has passed the gates yet.
+    pub fn power(&self) -> f32 {
+        let absolute_gated_power = match self.histogram.mean_power_above(ABSOLUTE_GATE_LKFS) {
+            Some(power) => power,
+            None => return 0.0,
+        };
+
+        let relative_gate_lkfs = power_to_lkfs(absolute_gated_power) + INTEGRATED_RELATIVE_GATE_LU;
+
+        self.histogram
+            .mean_power_above(relative_gate_lkfs)
+            .unwrap_or(0.0) as f32
+    }
+
+    pub fn reset(&mut self) {
+        self.blocks.reset();
+        self.histogram.reset();
+    }
+}
+
+pub struct LoudnessRange {
+    blocks: GatingBlocks,
+    histogram: Histogram,
+}
+
+impl LoudnessRange {
+    pub fn new(sample_rate_hz: f32, channels_num: usize) -> LoudnessRange {
+        LoudnessRange {
+            blocks: GatingBlocks::new(sample_rate_hz, channels_num, RANGE_STEPS_NUM_PER_BLOCK),
+            histogram: Histogram::new(),
+        }
+    }
+
+    /// Takes one sample per channel.
+    pub fn add_samples(&mut self, samples: &[f32]) {
+        if let Some(block_power) = self.blocks.add_samples(samples) {
+            self.histogram.add(block_power);
+        }
+    }
+
+    /// Returns the loudness range in LU, or 0 if no block has passed the gates yet.
+    pub fn range(&self) -> f32 {
+        let absolute_gated_power = match self.histogram.mean_power_above(ABSOLUTE_GATE_LKFS) {
+            Some(power) => power,
+            None => return 0.0,
+        };
+
+        let relative_gate_lkfs = power_to_lkfs(absolute_gated_power) + RANGE_RELATIVE_GATE_LU;
+
+        match (
+            self.histogram
+                .percentile_lkfs(relative_gate_lkfs, RANGE_LOW_PERCENTILE),
+            self.histogram
+                .percentile_lkfs(relative_gate_lkfs, RANGE_HIGH_PERCENTILE),
+        ) {
+            (Some(low), Some(high)) => high - low,
+            _ => 0.0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.blocks.reset();
+        self.histogram.reset();
+    }
+}
+
+// Splits the prefiltered input into overlapping gating blocks of a whole number of
+// steps, yielding the mean power of a block each time one completes.
+struct GatingBlocks {
+    samples_num_per_step: usize,
+
+    prefilters: ChannelPrefilters,
+
+    // Power sums of the last steps making up a block, and of the current step.
+    step_power_sums: Vec<f64>,
+    steps_num: usize,
+    current_power_sum: f64,
+    current_samples_num: usize,
+}
+
+impl GatingBlocks {
+    fn new(sample_rate_hz: f32, channels_num: usize, steps_num_per_block: usize) -> GatingBlocks {
+        GatingBlocks {
+            samples_num_per_step: ((sample_rate_hz * STEP_SECONDS).round() as usize).max(1),
+
+            prefilters: ChannelPrefilters::new(sample_rate_hz, channels_num),
+
+            step_power_sums: vec![0.0; steps_num_per_block],
+            steps_num: 0,
+            current_power_sum: 0.0,
+            current_samples_num: 0,
+        }
+    }
+
+    #[inline(always)]
+    fn add_samples(&mut self, samples: &[f32]) -> Option<f64> {
+        self.current_power_sum += self.prefilters.power(samples) as f64;
+        self.current_samples_num += 1;
+
+        if self.current_samples_num < self.samples_num_per_step {
+            return None;
+        }
+
+        let steps_num_per_block = self.step_power_sums.len();
+        self.step_power_sums[self.steps_num % steps_num_per_block] = self.current_power_sum;
+        self.steps_num += 1;
+        self.current_power_sum = 0.0;
+        self.current_samples_num = 0;
+
+        if self.steps_num < steps_num_per_block {
+            return None;
+        }
+
+        Some(
+            self.step_power_sums.iter().sum::<f64>()
+                / (steps_num_per_block * self.samples_num_per_step) as f64,
+        )
+    }
+
+    fn reset(&mut self) {
+        self.prefilters.reset();
+
+        self.step_power_sums.iter_mut().for_each(|sum| *sum = 0.0);
+        self.steps_num = 0;
+        self.current_power_sum = 0.0;
+        self.current_samples_num = 0;
+    }
+}
+
+struct Histogram {
+    bins: Vec<HistogramBin>,
+}
+
+#[derive(Clone)]
+struct HistogramBin {
+    blocks_num: u64,
+    power_sum: f64,
+}
+
+impl HistogramBin {
+    fn lkfs(&self) -> f32 {
+        power_to_lkfs(self.power_sum / self.blocks_num as f64)
+    }
+}
+
+impl Histogram {
+    fn new() -> Histogram {
+        let bins_num =
+            ((HISTOGRAM_MAX_LKFS - HISTOGRAM_MIN_LKFS) * HISTOGRAM_BINS_NUM_PER_LU) as usize;
+
+        Histogram {
+            bins: vec![
+                HistogramBin {
+                    blocks_num: 0,
+                    power_sum: 0.0,
+                };
+                bins_num
+            ],
+        }
+    }
+
+    fn add(&mut self, block_power: f64) {
+        let lkfs = power_to_lkfs(block_power);
+        if lkfs <= ABSOLUTE_GATE_LKFS {
+            return;
+        }
+
+        let index = ((lkfs - HISTOGRAM_MIN_LKFS) * HISTOGRAM_BINS_NUM_PER_LU) as usize;
+        let index = index.min(self.bins.len() - 1);
+        let bin = &mut self.bins[index];
+
+        bin.blocks_num += 1;
+        bin.power_sum += block_power;
+    }
+
+    // Bins holding blocks louder than gate_lkfs. Each bin is gated as a whole on
+    // the mean loudness of its blocks.
+    fn bins_above(&self, gate_lkfs: f32) -> impl Iterator<Item = &HistogramBin> {
+        self.bins
+            .iter()
+            .filter(move |bin| bin.blocks_num > 0 && bin.lkfs() > gate_lkfs)
+    }
+
+    fn mean_power_above(&self, gate_lkfs: f32) -> Option<f64> {
+        let mut blocks_num = 0;
+        let mut power_sum = 0.0;
+
+        for bin in self.bins_above(gate_lkfs) {
+            blocks_num += bin.blocks_num;
+            power_sum += bin.power_sum;
+        }
+
+        if blocks_num == 0 {
+            None
+        } else {
+            Some(power_sum / blocks_num as f64)
+        }
+    }
+
+    // Nearest-rank percentile (0..=1) of the loudness of the blocks above gate_lkfs.
+    fn percentile_lkfs(&self, gate_lkfs: f32, percentile: f64) -> Option<f32> {
+        let blocks_num: u64 = self.bins_above(gate_lkfs).map(|bin| bin.blocks_num).sum();
+        if blocks_num == 0 {
+            return None;
+        }
+
+        let rank = ((blocks_num - 1) as f64 * percentile).round() as u64;
+
+        let mut blocks_num_below = 0;
+        for bin in self.bins_above(gate_lkfs) {
+            blocks_num_below += bin.blocks_num;
+            if blocks_num_below > rank {
+                return Some(bin.lkfs());
+            }
+        }
+
+        None
+    }
+
+    fn reset(&mut self) {
+        for bin in &mut self.bins {
+            bin.blocks_num = 0;
+            bin.power_sum = 0.0;
+        }
+    }
+}
+
+/// Converts a mean square power, as the meters return it, to LKFS. Silence is
+/// `NEG_INFINITY`.
+#[inline(always)]
+pub fn power_to_lkfs(power: impl Into<f64>) -> f32 {
+    (-0.691 + 10.0 * power.into().log10()) as f32
+}
+
+// K-weights every channel and sums their powers with the BS.1770-4 channel weights.
+struct ChannelPrefilters {
+    prefilters: Vec<Prefilter>,
+    weights: Vec<f32>,
+}
+
+impl ChannelPrefilters {
+    fn new(sample_rate_hz: f32, channels_num: usize) -> ChannelPrefilters {
+        ChannelPrefilters {
+            prefilters: (0..channels_num)
+                .map(|_| Prefilter::new(sample_rate_hz))
+                .collect(),
+            weights: (0..channels_num).map(channels::weight).collect(),
+        }
+    }
+
+    #[inline(always)]
+    fn power(&mut self, samples: &[f32]) -> f32 {
+        let mut power = 0.0;
+
+        for ((prefilter, weight), sample) in
+            self.prefilters.iter_mut().zip(&self.weights).zip(samples)
+        {
+            let sample = prefilter.apply(*sample);
+            power += weight * sample * sample;
+        }
+
+        power
+    }
+
+    fn reset(&mut self) {
+        for prefilter in &mut self.prefilters {
+            prefilter.reset();
+        }
+    }
+}
+
 struct Prefilter {
     first: Filter,
     second: Filter,
@@ -78,6 +390,11 @@ impl Prefilter {
     fn apply(&mut self, sample: f32) -> f32 {
         self.second.apply(self.first.apply(sample))
     }
+
+    fn reset(&mut self) {
+        self.first.reset();
+        self.second.reset();
+    }
 }
 
 // struct Filter taken from https://github.com/ruuda/bs1770/blob/db97c508fa68fef3caec649f3ee756a810f2266f/src/lib.rs
@@ -158,4 +475,455 @@ impl Filter {
 
         y0
     }
+
+    /// Forget the past samples.
+    fn reset(&mut self) {
+        self.x1 = 0.0;
+        self.x2 = 0.0;
+        self.y1 = 0.0;
+        self.y2 = 0.0;
+    }
+}
+
+// Besides the unit tests, the synthetic test signals of EBU Tech 3341 (loudness
+// metering) and Tech 3342 (loudness range), generated at both common sample rates.
+// The cases built on programme material are left out.
+#[cfg(test)]
+mod tests {
+    use super::{power_to_lkfs, IntegratedLoudness, Loudness, LoudnessRange};
+    use crate::test_host::sine;
+
+    const SAMPLE_RATES_HZ: [f32; 2] = [44100.0, 48000.0];
+
+    // Tech 3341 allows ±0.1 LU on the momentary, short-term and integrated readings,
+    // Tech 3342 ±1 LU on the loudness range.
+    const LOUDNESS_TOLERANCE_LU: f32 = 0.1;
+    const RANGE_TOLERANCE_LU: f32 = 1.0;
+
+    // 1 kHz sine segments, each as dBFS per channel and seconds. Channels are in
+    // SMPTE order and NEG_INFINITY leaves one silent.
+    struct Segment {
+        dbfs: Vec<f32>,
+        seconds: f32,
+    }
+
+    fn stereo(dbfs: f32, seconds: f32) -> Segment {
+        Segment {
+            dbfs: vec![dbfs, dbfs],
+            seconds,
+        }
+    }
+
+    // The segments one after another, as one buffer per channel.
+    fn signal(sample_rate_hz: f32, segments: &[Segment]) -> Vec<Vec<f32>> {
+        let mut channels = vec![Vec::new(); segments[0].dbfs.len()];
+        for segment in segments {
+            for (channel, &dbfs) in channels.iter_mut().zip(&segment.dbfs) {
+                channel.append(&mut sine(sample_rate_hz, dbfs, segment.seconds)[0]);
+            }
+        }
+
+        channels
+    }
+
+    // All the readings of a meter run over the whole signal.
+    struct Readings {
+        max_momentary_lkfs: f32,
+        last_momentary_lkfs: f32,
+        max_short_term_lkfs: f32,
+        last_short_term_lkfs: f32,
+
+        // Every 100 ms, as a meter refreshes it.
+        short_term_lkfs: Vec<f32>,
+
+        integrated_lkfs: f32,
+        range_lu: f32,
+    }
+
+    fn measure(sample_rate_hz: f32, segments: &[Segment]) -> Readings {
+        let channels = signal(sample_rate_hz, segments);
+        let channels_num = channels.len();
+
+        let mut momentary = Loudness::new(
+            sample_rate_hz,
+            channels_num,
+            (sample_rate_hz * 0.4).round() as usize,
+            1,
+        );
+        let mut short_term = Loudness::new(
+            sample_rate_hz,
+            channels_num,
+            (sample_rate_hz * 3.0).round() as usize,
+            1,
+        );
+        let mut integrated_loudness = IntegratedLoudness::new(sample_rate_hz, channels_num);
+        let mut loudness_range = LoudnessRange::new(sample_rate_hz, channels_num);
+
+        let mut max_momentary_power = 0.0f32;
+        let mut momentary_power = 0.0;
+        let mut max_short_term_power = 0.0f32;
+        let mut short_term_power = 0.0;
+        let mut short_term_lkfs = Vec::new();
+        let samples_num_per_step = (sample_rate_hz * 0.1).round() as usize;
+
+        let mut samples = vec![0.0; channels_num];
+        for n in 0..channels[0].len() {
+            for (sample, channel) in samples.iter_mut().zip(&channels) {
+                *sample = channel[n];
+            }
+
+            momentary_power = momentary.add_samples(&samples).0;
+            max_momentary_power = max_momentary_power.max(momentary_power);
+            short_term_power = short_term.add_samples(&samples).0;
+            max_short_term_power = max_short_term_power.max(short_term_power);
+            integrated_loudness.add_samples(&samples);
+            loudness_range.add_samples(&samples);
+
+            if (n + 1) % samples_num_per_step == 0 {
+                short_term_lkfs.push(power_to_lkfs(short_term_power));
+            }
+        }
+
+        Readings {
+            max_momentary_lkfs: power_to_lkfs(max_momentary_power),
+            last_momentary_lkfs: power_to_lkfs(momentary_power),
+            max_short_term_lkfs: power_to_lkfs(max_short_term_power),
+            last_short_term_lkfs: power_to_lkfs(short_term_power),
+            short_term_lkfs,
+            integrated_lkfs: power_to_lkfs(integrated_loudness.power()),
+            range_lu: loudness_range.range(),
+        }
+    }
+
+    fn assert_reading(
+        name: &str,
+        sample_rate_hz: f32,
+        reading: f32,
+        expected: f32,
+        tolerance: f32,
+    ) {
+        assert!(
+            (reading - expected).abs() <= tolerance,
+            "{} is {} at {} Hz, expected {}±{}",
+            name,
+            reading,
+            sample_rate_hz,
+            expected,
+            tolerance
+        );
+    }
+
+    // The momentary loudness of one second of a 0 dBFS sine in channel `channel`
+    // alone, out of `channels_num`.
+    fn lkfs_in_channel(sample_rate_hz: f32, channels_num: usize, channel: usize) -> f32 {
+        let mut dbfs = vec![f32::NEG_INFINITY; channels_num];
+        dbfs[channel] = 0.0;
+
+        measure(sample_rate_hz, &[Segment { dbfs, seconds: 1.0 }]).last_momentary_lkfs
+    }
+
+    #[test]
+    fn same_reading_at_every_sample_rate() {
+        for sample_rate_hz in [44100.0, 48000.0, 88200.0, 96000.0] {
+            let lkfs = lkfs_in_channel(sample_rate_hz, 2, 0);
+            assert!(
+                (lkfs - (-3.01)).abs() < 0.05,
+                "{} LKFS at {} Hz",
+                lkfs,
+                sample_rate_hz
+            );
+        }
+    }
+
+    #[test]
+    fn surround_channel_weights() {
+        // 7.1 in SMPTE order: L R C LFE Ls Rs Lb Rb.
+        let lkfs_in_channel = |channel| lkfs_in_channel(48000.0, 8, channel);
+
+        assert!((lkfs_in_channel(2) - (-3.01)).abs() < 0.05);
+        assert!((lkfs_in_channel(4) - (-3.01 + 1.49)).abs() < 0.05);
+        assert!((lkfs_in_channel(5) - (-3.01 + 1.49)).abs() < 0.05);
+        assert_eq!(lkfs_in_channel(3), f32::NEG_INFINITY);
+
+        // The back surrounds are beyond ±120°, so not boosted.
+        assert!((lkfs_in_channel(6) - (-3.01)).abs() < 0.05);
+        assert!((lkfs_in_channel(7) - (-3.01)).abs() < 0.05);
+    }
+
+    #[test]
+    fn integrated_loudness_of_a_steady_sine() {
+        let lkfs = measure(48000.0, &[stereo(-23.0, 20.0)]).integrated_lkfs;
+        assert!((lkfs - (-23.0)).abs() < 0.1, "{} LKFS", lkfs);
+    }
+
+    #[test]
+    fn integrated_loudness_gates_out_quiet_parts() {
+        // The -36 dBFS part is below the relative gate, the -80 dBFS part below the
+        // absolute one; neither may pull the reading down.
+        let lkfs = measure(
+            48000.0,
+            &[
+                stereo(-80.0, 10.0),
+                stereo(-23.0, 60.0),
+                stereo(-36.0, 10.0),
+            ],
+        )
+        .integrated_lkfs;
+        assert!((lkfs - (-23.0)).abs() < 0.1, "{} LKFS", lkfs);
+    }
+
+    #[test]
+    fn integrated_loudness_without_gated_blocks() {
+        let mut integrated_loudness = IntegratedLoudness::new(48000.0, 2);
+        assert_eq!(integrated_loudness.power(), 0.0);
+
+        for _ in 0..48000 {
+            integrated_loudness.add_samples(&[0.0, 0.0]);
+        }
+        assert_eq!(integrated_loudness.power(), 0.0);
+    }
+
+    #[test]
+    fn integrated_loudness_reset() {
+        let mut integrated_loudness = IntegratedLoudness::new(48000.0, 2);
+
+        for sample in sine(48000.0, 0.0, 1.0).swap_remove(0) {
+            integrated_loudness.add_samples(&[sample, sample]);
+        }
+        assert!(integrated_loudness.power() > 0.0);
+
+        integrated_loudness.reset();
+        assert_eq!(integrated_loudness.power(), 0.0);
+    }
+
+    #[test]
+    fn loudness_range_of_a_steady_sine() {
+        let lu = measure(48000.0, &[stereo(-23.0, 20.0)]).range_lu;
+        assert!(lu.abs() < 0.1, "{} LU", lu);
+    }
+
+    #[test]
+    fn loudness_range_between_two_levels() {
+        // EBU Tech 3342 test case 1: 20 s at -20 dBFS then 20 s at -30 dBFS.
+        let lu = measure(48000.0, &[stereo(-20.0, 20.0), stereo(-30.0, 20.0)]).range_lu;
+        assert!((lu - 10.0).abs() < 1.0, "{} LU", lu);
+    }
+
+    #[test]
+    fn loudness_range_gates_out_quiet_parts() {
+        // The -50 dBFS part is more than 20 LU below the rest and may not widen it.
+        let lu = measure(
+            48000.0,
+            &[
+                stereo(-20.0, 20.0),
+                stereo(-30.0, 20.0),
+                stereo(-50.0, 20.0),
+            ],
+        )
+        .range_lu;
+        assert!((lu - 10.0).abs() < 1.0, "{} LU", lu);
+    }
+
+    // Cases 1 and 2: a steady stereo sine reads its own level on every meter.
+    #[test]
+    fn tech_3341_cases_1_and_2_steady_sine() {
+        for sample_rate_hz in SAMPLE_RATES_HZ {
+            for dbfs in [-23.0, -33.0] {
+                let readings = measure(sample_rate_hz, &[stereo(dbfs, 20.0)]);
+
+                for (name, reading) in [
+                    ("momentary", readings.last_momentary_lkfs),
+                    ("short-term", readings.last_short_term_lkfs),
+                    ("integrated", readings.integrated_lkfs),
+                ] {
+                    assert_reading(name, sample_rate_hz, reading, dbfs, LOUDNESS_TOLERANCE_LU);
+                }
+            }
+        }
+    }
+
+    // Cases 3 and 4: parts below the relative and the absolute gate.
+    #[test]
+    fn tech_3341_cases_3_and_4_gating() {
+        for sample_rate_hz in SAMPLE_RATES_HZ {
+            let readings = measure(
+                sample_rate_hz,
+                &[
+                    stereo(-36.0, 10.0),
+                    stereo(-23.0, 60.0),
+                    stereo(-36.0, 10.0),
+                ],
+            );
+            assert_reading(
+                "integrated",
+                sample_rate_hz,
+                readings.integrated_lkfs,
+                -23.0,
+                LOUDNESS_TOLERANCE_LU,
+            );
+
+            let readings = measure(
+                sample_rate_hz,
+                &[
+                    stereo(-72.0, 10.0),
+                    stereo(-36.0, 10.0),
+                    stereo(-23.0, 60.0),
+                    stereo(-36.0, 10.0),
+                    stereo(-72.0, 10.0),
+                ],
+            );
+            assert_reading(
+                "integrated",
+                sample_rate_hz,
+                readings.integrated_lkfs,
+                -23.0,
+                LOUDNESS_TOLERANCE_LU,
+            );
+        }
+    }
+
+    // Case 5: levels either side of the target, all above the relative gate.
+    #[test]
+    fn tech_3341_case_5_changing_level() {
+        for sample_rate_hz in SAMPLE_RATES_HZ {
+            let readings = measure(
+                sample_rate_hz,
+                &[
+                    stereo(-26.0, 20.0),
+                    stereo(-20.0, 20.1),
+                    stereo(-26.0, 20.0),
+                ],
+            );
+            assert_reading(
+                "integrated",
+                sample_rate_hz,
+                readings.integrated_lkfs,
+                -23.0,
+                LOUDNESS_TOLERANCE_LU,
+            );
+        }
+    }
+
+    // Case 6: 5.0 channels, L R C Ls Rs, with the LFE slot left silent.
+    #[test]
+    fn tech_3341_case_6_surround() {
+        let dbfs = [-28.0, -28.0, -24.0, f32::NEG_INFINITY, -30.0, -30.0];
+
+        for sample_rate_hz in SAMPLE_RATES_HZ {
+            let readings = measure(
+                sample_rate_hz,
+                &[Segment {
+                    dbfs: dbfs.to_vec(),
+                    seconds: 20.0,
+                }],
+            );
+            assert_reading(
+                "integrated",
+                sample_rate_hz,
+                readings.integrated_lkfs,
+                -23.0,
+                LOUDNESS_TOLERANCE_LU,
+            );
+        }
+    }
+
+    // Case 9: 1.34 s at -20 dBFS then 1.66 s at -30 dBFS over and over. Every 3 s
+    // window holds one period, so the short-term reading stays at -23 once the
+    // first window has filled.
+    #[test]
+    fn tech_3341_case_9_short_term_of_a_changing_level() {
+        let segments: Vec<Segment> = (0..5)
+            .flat_map(|_| [stereo(-20.0, 1.34), stereo(-30.0, 1.66)])
+            .collect();
+
+        for sample_rate_hz in SAMPLE_RATES_HZ {
+            let readings = measure(sample_rate_hz, &segments);
+
+            for &reading in &readings.short_term_lkfs[29..] {
+                assert_reading(
+                    "short-term",
+                    sample_rate_hz,
+                    reading,
+                    -23.0,
+                    LOUDNESS_TOLERANCE_LU,
+                );
+            }
+        }
+    }
+
+    // Cases 10 and 12 check that a burst filling the window just once is still
+    // caught at its full level, wherever it starts: here bursts of exactly 3 s and
+    // 0.4 s at -23 dBFS, after silences that shift them by parts of 100 ms.
+    #[test]
+    fn tech_3341_cases_10_and_12_bursts() {
+        for sample_rate_hz in SAMPLE_RATES_HZ {
+            for offset_seconds in [0.0, 0.02, 0.05, 0.09] {
+                let readings = measure(
+                    sample_rate_hz,
+                    &[
+                        stereo(f32::NEG_INFINITY, 1.0 + offset_seconds),
+                        stereo(-23.0, 3.0),
+                        stereo(f32::NEG_INFINITY, 1.0),
+                    ],
+                );
+                assert_reading(
+                    "max short-term",
+                    sample_rate_hz,
+                    readings.max_short_term_lkfs,
+                    -23.0,
+                    LOUDNESS_TOLERANCE_LU,
+                );
+
+                let readings = measure(
+                    sample_rate_hz,
+                    &[
+                        stereo(f32::NEG_INFINITY, 1.0 + offset_seconds),
+                        stereo(-23.0, 0.4),
+                        stereo(f32::NEG_INFINITY, 1.0),
+                    ],
+                );
+                assert_reading(
+                    "max momentary",
+                    sample_rate_hz,
+                    readings.max_momentary_lkfs,
+                    -23.0,
+                    LOUDNESS_TOLERANCE_LU,
+                );
+            }
+        }
+    }
+
+    // Cases 1 to 4: two or more levels, the quietest of case 4 below the relative
+    // gate.
+    #[test]
+    fn tech_3342_cases_1_to_4_loudness_range() {
+        let cases = [
+            (vec![stereo(-20.0, 20.0), stereo(-30.0, 20.0)], 10.0),
+            (vec![stereo(-20.0, 20.0), stereo(-15.0, 20.0)], 5.0),
+            (vec![stereo(-40.0, 20.0), stereo(-20.0, 20.0)], 20.0),
+            (
+                vec![
+                    stereo(-50.0, 20.0),
+                    stereo(-35.0, 20.0),
+                    stereo(-20.0, 20.0),
+                    stereo(-35.0, 20.0),
+                    stereo(-50.0, 20.0),
+                ],
+                15.0,
+            ),
+        ];
+
+        for sample_rate_hz in SAMPLE_RATES_HZ {
+            for (segments, lu) in &cases {
+                let readings = measure(sample_rate_hz, segments);
+                assert_reading(
+                    "loudness range",
+                    sample_rate_hz,
+                    readings.range_lu,
+                    *lu,
+                    RANGE_TOLERANCE_LU,
+                );
+            }
+        }
+    }
 }
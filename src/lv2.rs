@@ -0,0 +1,504 @@
+use std::ffi::CString;
+use std::os::raw::{c_char, c_void};
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+use std::{fs, io, ptr};
+
+use vst::buffer::AudioBuffer;
+use vst::plugin::HostCallback;
+
+use crate::params::Params;
+use crate::wrapped::{slug, Instance, WrappedPlugin};
+
+const URL: &str = "https://github.com/youxkei/jimtel";
+const LICENSE: &str = "https://creativecommons.org/publicdomain/zero/1.0/";
+
+// Stereo, the first two channels of the 7.1 layout the plugins process.
+const AUDIO_PORTS: [(&str, &str, bool); 4] = [
+    ("in_l", "Input L", true),
+    ("in_r", "Input R", true),
+    ("out_l", "Output L", false),
+    ("out_r", "Output R", false),
+];
+const AUDIO_CHANNELS_NUM: usize = 2;
+
+// `run` passes the block to the plugin in chunks of up to this many samples, so
+// the copy of the input never has to grow on the audio thread.
+const CHUNK_SAMPLES_NUM: usize = 1024;
+
+// Ports are the audio ports, then the latency, then one per param and one per
+// meter, both in index order.
+const LATENCY_PORT: usize = AUDIO_PORTS.len();
+const FIRST_PARAM_PORT: usize = LATENCY_PORT + 1;
+
+/// The `LV2_Descriptor` of the LV2 core spec.
+#[repr(C)]
+pub struct Lv2Descriptor {
+    uri: *const c_char,
+    instantiate: unsafe extern "C" fn(
+        descriptor: *const Lv2Descriptor,
+        sample_rate: f64,
+        bundle_path: *const c_char,
+        features: *const *const c_void,
+    ) -> *mut c_void,
+    connect_port: unsafe extern "C" fn(instance: *mut c_void, port: u32, data: *mut c_void),
+    activate: Option<unsafe extern "C" fn(instance: *mut c_void)>,
+    run: unsafe extern "C" fn(instance: *mut c_void, sample_count: u32),
+    deactivate: Option<unsafe extern "C" fn(instance: *mut c_void)>,
+    cleanup: unsafe extern "C" fn(instance: *mut c_void),
+    extension_data: Option<unsafe extern "C" fn(uri: *const c_char) -> *const c_void>,
+}
+
+// The one plugin of the library, set up by the first `descriptor` call.
+static LIBRARY: OnceLock<Library> = OnceLock::new();
+
+struct Library {
+    descriptor: Lv2Descriptor,
+    new_plugin: fn(sample_rate: f64) -> Lv2Plugin,
+
+    // What the descriptor points into.
+    _uri: CString,
+}
+
+// The descriptor only points into the URI kept alongside it, which is never
+// changed.
+unsafe impl Send for Library {}
+unsafe impl Sync for Library {}
+
+impl Library {
+    fn new<P: WrappedPlugin>() -> Self {
+        let uri =
+            CString::new(uri(&P::new(HostCallback::default()).get_info().name)).unwrap_or_default();
+
+        Self {
+            descriptor: Lv2Descriptor {
+                uri: uri.as_ptr(),
+                instantiate,
+                connect_port,
+                activate: Some(activate),
+                run,
+                deactivate: Some(deactivate),
+                cleanup,
+                extension_data: None,
+            },
+            new_plugin: Lv2Plugin::new::<P>,
+            _uri: uri,
+        }
+    }
+}
+
+struct Lv2Plugin {
+    instance: Box<dyn Instance>,
+    params: Arc<dyn Params + Send + Sync>,
+    params_num: usize,
+    meters_num: usize,
+
+    // Where the host connected each port, null until it does.
+    ports: Vec<*mut f32>,
+
+    // The param values last read from the ports, so only changes are applied.
+    applied: Vec<f32>,
+
+    // The host may hand the same buffer in and out, so each chunk of the input is
+    // copied first.
+    inputs: [Vec<f32>; AUDIO_CHANNELS_NUM],
+}
+
+impl Lv2Plugin {
+    fn new<P: WrappedPlugin>(sample_rate: f64) -> Self {
+        let mut plugin = P::new(HostCallback::default());
+        plugin.init();
+        plugin.set_sample_rate(sample_rate as f32);
+
+        let params_num = P::Params::num_params();
+        let meters_num = P::Params::num_meters();
+
+        Self {
+            params: plugin.params(),
+            instance: Box::new(plugin),
+            params_num,
+            meters_num,
+
+            ports: vec![ptr::null_mut(); FIRST_PARAM_PORT + params_num + meters_num],
+            applied: vec![f32::NAN; params_num],
+            inputs: [vec![0.0; CHUNK_SAMPLES_NUM], vec![0.0; CHUNK_SAMPLES_NUM]],
+        }
+    }
+
+    fn read_params(&mut self) {
+        for index in 0..self.params_num {
+            let port = self.ports[FIRST_PARAM_PORT + index];
+            if port.is_null() {
+                continue;
+            }
+
+            let value = unsafe { *port };
+            if value.is_nan() || value == self.applied[index] {
+                continue;
+            }
+
+            let range = self.params.get_range(index as i32);
+            self.params
+                .set_value(index as i32, value.max(*range.start()).min(*range.end()));
+            self.applied[index] = value;
+        }
+    }
+
+    fn write_outputs(&self) {
+        let latency = self.ports[LATENCY_PORT];
+        if !latency.is_null() {
+            unsafe { *latency = self.instance.latency_samples() as f32 };
+        }
+
+        for index in 0..self.meters_num {
+            let port = self.ports[FIRST_PARAM_PORT + self.params_num + index];
+            if port.is_null() {
+                continue;
+            }
+
+            // Kept in the declared range: a silent dB meter reads -inf.
+            let range = self.params.get_meter_range(index as i32);
+            let value = self.params.get_meter_value(index as i32);
+            unsafe { *port = value.max(*range.start()).min(*range.end()) };
+        }
+    }
+}
+
+/// Returns the descriptor of `P` for `lv2_descriptor`, which `jimtel::lv2_main!`
+/// exports.
+pub fn descriptor<P: WrappedPlugin>(index: u32) -> *const Lv2Descriptor {
+    if index != 0 {
+        return ptr::null();
+    }
+
+    &LIBRARY.get_or_init(Library::new::<P>).descriptor
+}
+
+unsafe extern "C" fn instantiate(
+    _descriptor: *const Lv2Descriptor,
+    sample_rate: f64,
+    _bundle_path: *const c_char,
+    _features: *const *const c_void,
+) -> *mut c_void {
+    match LIBRARY.get() {
+        Some(library) => Box::into_raw(Box::new((library.new_plugin)(sample_rate))) as *mut c_void,
+        None => ptr::null_mut(),
+    }
+}
+
+unsafe extern "C" fn connect_port(instance: *mut c_void, port: u32, data: *mut c_void) {
+    let plugin = &mut *(instance as *mut Lv2Plugin);
+
+    if let Some(slot) = plugin.ports.get_mut(port as usize) {
+        *slot = data as *mut f32;
+    }
+}
+
+unsafe extern "C" fn activate(instance: *mut c_void) {
+    let plugin = &mut *(instance as *mut Lv2Plugin);
+
+    plugin.instance.plugin().resume();
+}
+
+unsafe extern "C" fn run(instance: *mut c_void, sample_count: u32) {
+    let plugin = &mut *(instance as *mut Lv2Plugin);
+    let samples_num = sample_count as usize;
+
+    if plugin.ports[AUDIO_CHANNELS_NUM..AUDIO_PORTS.len()]
+        .iter()
+        .any(|output| output.is_null())
+    {
+        return;
+    }
+
+    plugin.read_params();
+
+    for start in (0..samples_num).step_by(CHUNK_SAMPLES_NUM) {
+        let chunk_samples_num = (samples_num - start).min(CHUNK_SAMPLES_NUM);
+
+        let mut inputs = [ptr::null(); AUDIO_CHANNELS_NUM];
+        let mut outputs = [ptr::null_mut(); AUDIO_CHANNELS_NUM];
+        for channel in 0..AUDIO_CHANNELS_NUM {
+            let input = plugin.ports[channel];
+            let copy = &mut plugin.inputs[channel][..chunk_samples_num];
+            if input.is_null() {
+                copy.fill(0.0);
+            } else {
+                copy.copy_from_slice(std::slice::from_raw_parts(
+                    input.add(start),
+                    chunk_samples_num,
+                ));
+            }
+
+            inputs[channel] = copy.as_ptr();
+            outputs[channel] = plugin.ports[AUDIO_CHANNELS_NUM + channel].add(start);
+        }
+
+        let mut buffer = AudioBuffer::from_raw(
+            AUDIO_CHANNELS_NUM,
+            AUDIO_CHANNELS_NUM,
+            inputs.as_ptr(),
+            outputs.as_mut_ptr(),
+            chunk_samples_num,
+        );
+        plugin.instance.plugin().process(&mut buffer);
+    }
+
+    plugin.write_outputs();
+}
+
+unsafe extern "C" fn deactivate(instance: *mut c_void) {
+    let plugin = &mut *(instance as *mut Lv2Plugin);
+
+    plugin.instance.plugin().suspend();
+}
+
+unsafe extern "C" fn cleanup(instance: *mut c_void) {
+    drop(Box::from_raw(instance as *mut Lv2Plugin));
+}
+
+/// The URI the LV2 plugin named `name` is known by.
+pub fn uri(name: &str) -> String {
+    format!("{}#{}", URL, slug(name))
+}
+
+/// Writes the TTL of `P` into the bundle directory `dir`, next to which `binary`
+/// is the file name of the library.
+pub fn write_bundle<P: WrappedPlugin>(dir: &Path, binary: &str) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    fs::write(dir.join("manifest.ttl"), manifest_ttl::<P>(binary))?;
+    fs::write(dir.join("plugin.ttl"), plugin_ttl::<P>())
+}
+
+/// The `manifest.ttl` of the bundle, which points hosts at the library and the
+/// plugin description.
+pub fn manifest_ttl<P: WrappedPlugin>(binary: &str) -> String {
+    let name = P::new(HostCallback::default()).get_info().name;
+
+    format!(
+        "@prefix lv2: <http://lv2plug.in/ns/lv2core#> .\n\
+         @prefix rdfs: <http://www.w3.org/2000/01/rdf-schema#> .\n\
+         \n\
+         <{}>\n    a lv2:Plugin ;\n    lv2:binary <{}> ;\n    rdfs:seeAlso <plugin.ttl> .\n",
+        uri(&name),
+        binary,
+    )
+}
+
+/// The `plugin.ttl` of the bundle, describing the ports from the `Params` of `P`:
+/// its params as control inputs and its meters as control outputs, with their
+/// names, units, ranges and defaults.
+pub fn plugin_ttl<P: WrappedPlugin>() -> String {
+    let plugin = P::new(HostCallback::default());
+    let name = plugin.get_info().name;
+    let params = plugin.params();
+    let uri = uri(&name);
+
+    let mut ports = Vec::new();
+
+    for (index, &(symbol, port_name, is_input)) in AUDIO_PORTS.iter().enumerate() {
+        ports.push(vec![
+            format!("a lv2:AudioPort, {}", port_direction(is_input)),
+            format!("lv2:index {}", index),
+            format!("lv2:symbol {}", ttl_string(symbol)),
+            format!("lv2:name {}", ttl_string(port_name)),
+        ]);
+    }
+
+    ports.push(vec![
+        "a lv2:ControlPort, lv2:OutputPort".to_string(),
+        format!("lv2:index {}", LATENCY_PORT),
+        "lv2:symbol \"latency\"".to_string(),
+        "lv2:name \"latency\"".to_string(),
+        "lv2:designation lv2:latency".to_string(),
+        "lv2:portProperty lv2:reportsLatency, lv2:integer".to_string(),
+        "units:unit units:frame".to_string(),
+    ]);
+
+    let mut sections = Vec::new();
+
+    for index in P::Params::index_range() {
+        let range = params.get_range(index);
+        let default = params
+            .get_default(index)
+            .max(*range.start())
+            .min(*range.end());
+
+        let mut port = vec![
+            "a lv2:ControlPort, lv2:InputPort".to_string(),
+            format!("lv2:index {}", FIRST_PARAM_PORT + index as usize),
+            format!("lv2:symbol {}", ttl_string(&params.get_name(index))),
+            format!("lv2:name {}", ttl_string(&params.get_name(index))),
+            format!("lv2:default {}", ttl_number(default)),
+            format!("lv2:minimum {}", ttl_number(*range.start())),
+            format!("lv2:maximum {}", ttl_number(*range.end())),
+        ];
+
+        let choices = params.get_choices(index);
+        if !choices.is_empty() {
+            port.push("lv2:portProperty lv2:integer, lv2:enumeration".to_string());
+            let scale_points = choices
+                .iter()
+                .enumerate()
+                .map(|(value, choice)| {
+                    format!(
+                        "[ rdfs:label {} ; rdf:value {} ]",
+                        ttl_string(choice),
+                        value
+                    )
+                })
+                .collect::<Vec<_>>();
+            port.push(format!("lv2:scalePoint {}", scale_points.join(", ")));
+        } else if params.is_button(index) || params.is_checkbox(index) {
+            port.push("lv2:portProperty lv2:toggled".to_string());
+        } else {
+            let unit = params.get_unit(index);
+            if unit == "samples" {
+                port.push("lv2:portProperty lv2:integer".to_string());
+            }
+            if let Some(unit) = ttl_unit(&unit) {
+                port.push(format!("units:unit {}", unit));
+            }
+        }
+
+        let section = params.get_section(index);
+        if !section.is_empty() {
+            port.push(format!("pg:group <{}_{}>", uri, slug(&section)));
+            if !sections.contains(&section) {
+                sections.push(section);
+            }
+        }
+
+        ports.push(port);
+    }
+
+    for index in P::Params::meter_index_range() {
+        let range = params.get_meter_range(index);
+
+        let mut port = vec![
+            "a lv2:ControlPort, lv2:OutputPort".to_string(),
+            format!(
+                "lv2:index {}",
+                FIRST_PARAM_PORT + P::Params::num_params() + index as usize
+            ),
+            format!("lv2:symbol {}", ttl_string(&params.get_meter_name(index))),
+            format!("lv2:name {}", ttl_string(&params.get_meter_name(index))),
+            format!("lv2:minimum {}", ttl_number(*range.start())),
+            format!("lv2:maximum {}", ttl_number(*range.end())),
+        ];
+        if let Some(unit) = ttl_unit(&params.get_meter_unit(index)) {
+            port.push(format!("units:unit {}", unit));
+        }
+
+        ports.push(port);
+    }
+
+    let mut ttl = String::from(
+        "@prefix doap: <http://usefulinc.com/ns/doap#> .\n\
+         @prefix lv2: <http://lv2plug.in/ns/lv2core#> .\n\
+         @prefix pg: <http://lv2plug.in/ns/ext/port-groups#> .\n\
+         @prefix rdf: <http://www.w3.org/1999/02/22-rdf-syntax-ns#> .\n\
+         @prefix rdfs: <http://www.w3.org/2000/01/rdf-schema#> .\n\
+         @prefix units: <http://lv2plug.in/ns/extensions/units#> .\n\n",
+    );
+
+    for section in &sections {
+        ttl += &format!(
+            "<{}_{}>\n    a pg:Group ;\n    lv2:symbol {} ;\n    rdfs:label {} .\n\n",
+            uri,
+            slug(section),
+            ttl_string(&slug(section)),
+            ttl_string(section),
+        );
+    }
+
+    // LV2 hosts pick the newest of two bundles by minor and micro version.
+    let mut version = env!("CARGO_PKG_VERSION").split('.').skip(1);
+    let minor_version = version.next().unwrap_or("0");
+    let micro_version = version.next().unwrap_or("0");
+
+    let ports = ports
+        .iter()
+        .map(|port| format!("[\n        {}\n    ]", port.join(" ;\n        ")))
+        .collect::<Vec<_>>();
+
+    ttl += &format!(
+        "<{}>\n    a lv2:Plugin, lv2:LimiterPlugin ;\n    doap:name {} ;\n    \
+         doap:license <{}> ;\n    lv2:minorVersion {} ;\n    lv2:microVersion {} ;\n    \
+         lv2:port {} .\n",
+        uri,
+        ttl_string(&name),
+        LICENSE,
+        minor_version,
+        micro_version,
+        ports.join(", "),
+    );
+
+    ttl
+}
+
+fn port_direction(is_input: bool) -> &'static str {
+    if is_input {
+        "lv2:InputPort"
+    } else {
+        "lv2:OutputPort"
+    }
+}
+
+// The LV2 unit of a param unit. Units LV2 has no term for are described inline.
+fn ttl_unit(unit: &str) -> Option<String> {
+    match unit {
+        "" => None,
+        "dB" => Some("units:db".to_string()),
+        "ms" => Some("units:ms".to_string()),
+        "samples" => Some("units:frame".to_string()),
+        _ => Some(format!(
+            "[ a units:Unit ; rdfs:label {} ; units:symbol {} ; units:render {} ]",
+            ttl_string(unit),
+            ttl_string(unit),
+            ttl_string(&format!("%f {}", unit)),
+        )),
+    }
+}
+
+fn ttl_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// Turtle has no literal for infinity, so it becomes the largest finite value.
+fn ttl_number(value: f32) -> String {
+    format!("{:?}", value.clamp(f32::MIN, f32::MAX))
+}
+
+/// Exports the plugin as LV2 next to its VST2 entry point, from the same library.
+/// The TTL of the bundle comes from `jimtel::lv2::write_bundle`.
+#[macro_export]
+macro_rules! lv2_main {
+    ($plugin:ty) => {
+        #[no_mangle]
+        pub extern "C" fn lv2_descriptor(index: u32) -> *const $crate::lv2::Lv2Descriptor {
+            $crate::lv2::descriptor::<$plugin>(index)
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ttl_number, ttl_string, ttl_unit};
+
+    #[test]
+    fn numbers_are_valid_turtle_literals() {
+        assert_eq!(ttl_number(-80.0), "-80.0");
+        assert_eq!(ttl_number(0.5), "0.5");
+        assert_eq!(ttl_number(f32::NEG_INFINITY), format!("{:?}", f32::MIN));
+    }
+
+    #[test]
+    fn units_without_an_lv2_term_are_described_inline() {
+        assert_eq!(ttl_unit(""), None);
+        assert_eq!(ttl_unit("dB").unwrap(), "units:db");
+        assert_eq!(ttl_unit("ms").unwrap(), "units:ms");
+        assert!(ttl_unit("LKFS").unwrap().contains("units:symbol \"LKFS\""));
+        assert_eq!(ttl_string("say \"hi\""), "\"say \\\"hi\\\"\"");
+    }
+}
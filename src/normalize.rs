@@ -0,0 +1,203 @@
+use crate::brickwall::Brickwall;
+use crate::loudness::{power_to_lkfs, IntegratedLoudness};
+use crate::true_peak::TruePeak;
+
+// The true-peak detector reports a peak up to 12 samples after the sample causing
+// it, so a measurement runs on over as many samples of silence.
+const TRUE_PEAK_TAIL_SAMPLES_NUM: usize = 12;
+
+// The same lookahead and release as the true peak limiter of the limiter plugin.
+const BRICKWALL_LOOKAHEAD_MS: f32 = 1.5;
+const BRICKWALL_RELEASE_MS: f32 = 100.0;
+
+/// What the first pass finds in the whole of the audio.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Measurement {
+    /// -∞ for audio with no block above the absolute gate.
+    pub integrated_lkfs: f32,
+    pub true_peak_dbtp: f32,
+}
+
+/// What the second pass aims for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Target {
+    pub integrated_lkfs: f32,
+    pub true_peak_ceiling_dbtp: f32,
+}
+
+/// What the second pass did.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Normalization {
+    pub gain_db: f32,
+
+    /// Whether the gain would have taken the true peak over the ceiling, so the
+    /// audio went through the true-peak brickwall as well.
+    pub limited: bool,
+}
+
+/// The first pass: measures the integrated loudness and the true peak of `channels`,
+/// one buffer per channel.
+pub fn measure(sample_rate_hz: f32, channels: &[Vec<f32>]) -> Measurement {
+    let channels_num = channels.len();
+    let samples_num = channels.first().map_or(0, |channel| channel.len());
+
+    let mut integrated_loudness = IntegratedLoudness::new(sample_rate_hz, channels_num);
+    let mut true_peak = TruePeak::new(channels_num);
+    let mut max_true_peak = 0.0f32;
+
+    let mut samples = vec![0.0; channels_num];
+    for index in 0..samples_num {
+        for (sample, channel) in samples.iter_mut().zip(channels) {
+            *sample = channel[index];
+        }
+
+        integrated_loudness.add_samples(&samples);
+        max_true_peak = max_true_peak.max(true_peak.add_samples(&samples));
+    }
+
+    samples.iter_mut().for_each(|sample| *sample = 0.0);
+    for _ in 0..TRUE_PEAK_TAIL_SAMPLES_NUM {
+        max_true_peak = max_true_peak.max(true_peak.add_samples(&samples));
+    }
+
+    Measurement {
+        integrated_lkfs: power_to_lkfs(integrated_loudness.power()),
+        true_peak_dbtp: 20.0 * max_true_peak.log10(),
+    }
+}
+
+/// The second pass: applies the static gain taking the `measurement` of `channels`
+/// to the target loudness. Only if that would push the true peak over the ceiling
+/// does the audio also go through the true-peak brickwall, which then holds the
+/// gain down around the peaks alone; its latency is compensated.
+///
+/// Audio measured as silent is left as it is.
+pub fn normalize(
+    sample_rate_hz: f32,
+    channels: &mut [Vec<f32>],
+    measurement: &Measurement,
+    target: &Target,
+) -> Normalization {
+    if !measurement.integrated_lkfs.is_finite() {
+        return Normalization {
+            gain_db: 0.0,
+            limited: false,
+        };
+    }
+
+    let gain_db = target.integrated_lkfs - measurement.integrated_lkfs;
+    let gain = 10f32.powf(gain_db / 20.0);
+    let limited = measurement.true_peak_dbtp + gain_db > target.true_peak_ceiling_dbtp;
+
+    if limited {
+        limit(
+            sample_rate_hz,
+            channels,
+            gain,
+            target.true_peak_ceiling_dbtp,
+        );
+    } else {
+        for channel in channels.iter_mut() {
+            channel.iter_mut().for_each(|sample| *sample *= gain);
+        }
+    }
+
+    Normalization { gain_db, limited }
+}
+
+// Applies the gain and the brickwall, writing each output sample back where its
+// input was.
+fn limit(sample_rate_hz: f32, channels: &mut [Vec<f32>], gain: f32, ceiling_dbtp: f32) {
+    let channels_num = channels.len();
+    let samples_num = channels.first().map_or(0, |channel| channel.len());
+
+    let mut brickwall = Brickwall::new(sample_rate_hz, channels_num, BRICKWALL_LOOKAHEAD_MS);
+    brickwall.set_ceiling(10f32.powf(ceiling_dbtp / 20.0));
+    brickwall.set_release(BRICKWALL_RELEASE_MS);
+    let latency = brickwall.latency();
+
+    let mut samples = vec![0.0; channels_num];
+    for index in 0..samples_num + latency {
+        for (sample, channel) in samples.iter_mut().zip(channels.iter()) {
+            *sample = channel.get(index).map_or(0.0, |sample| sample * gain);
+        }
+
+        brickwall.add_samples(&mut samples);
+
+        if index >= latency {
+            for (sample, channel) in samples.iter().zip(channels.iter_mut()) {
+                channel[index - latency] = *sample;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{measure, normalize, Target};
+    use crate::test_host;
+
+    fn sine(dbfs: f32, seconds: f32) -> Vec<Vec<f32>> {
+        test_host::sine(48000.0, dbfs, seconds)
+    }
+
+    #[test]
+    fn quiet_audio_gets_a_static_gain() {
+        let mut channels = sine(-30.0, 10.0);
+        let original = channels.clone();
+
+        let measurement = measure(48000.0, &channels);
+        assert!((measurement.integrated_lkfs - (-30.0)).abs() < 0.1);
+
+        let target = Target {
+            integrated_lkfs: -16.0,
+            true_peak_ceiling_dbtp: -1.0,
+        };
+        let normalization = normalize(48000.0, &mut channels, &measurement, &target);
+        assert!(!normalization.limited);
+
+        let gain = 10f32.powf(normalization.gain_db / 20.0);
+        for (sample, original) in channels[0].iter().zip(&original[0]) {
+            assert_eq!(*sample, original * gain);
+        }
+
+        let lkfs = measure(48000.0, &channels).integrated_lkfs;
+        assert!((lkfs - (-16.0)).abs() < 0.1, "{} LKFS", lkfs);
+    }
+
+    #[test]
+    fn peaks_over_the_ceiling_are_limited() {
+        let mut channels = sine(-23.0, 10.0);
+        let measurement = measure(48000.0, &channels);
+
+        // The gain alone would take the peak to -5 dBTP.
+        let target = Target {
+            integrated_lkfs: -5.0,
+            true_peak_ceiling_dbtp: -8.0,
+        };
+        let normalization = normalize(48000.0, &mut channels, &measurement, &target);
+        assert!(normalization.limited);
+
+        let measurement = measure(48000.0, &channels);
+        assert!(
+            measurement.true_peak_dbtp <= -8.0 + 0.05,
+            "{} dBTP",
+            measurement.true_peak_dbtp
+        );
+    }
+
+    #[test]
+    fn silence_is_left_alone() {
+        let mut channels = vec![vec![0.0; 48000]; 2];
+        let measurement = measure(48000.0, &channels);
+        assert_eq!(measurement.integrated_lkfs, f32::NEG_INFINITY);
+
+        let target = Target {
+            integrated_lkfs: -23.0,
+            true_peak_ceiling_dbtp: -1.0,
+        };
+        let normalization = normalize(48000.0, &mut channels, &measurement, &target);
+        assert_eq!(normalization.gain_db, 0.0);
+        assert!(channels.iter().flatten().all(|&sample| sample == 0.0));
+    }
+}
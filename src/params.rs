@@ -1,17 +1,39 @@
+/// Version of the bank data written by the `Params` derive. Version 0 is the legacy
+/// positional `Vec<f32>`, which is still read.
+pub const BANK_DATA_VERSION: u32 = 1;
+
 pub trait Params {
-    fn num_params() -> usize;
-    fn index_range() -> std::ops::Range<i32>;
+    // Not callable through `dyn Params`, which format wrappers hold instead of the
+    // concrete type.
+    fn num_params() -> usize
+    where
+        Self: Sized;
+    fn index_range() -> std::ops::Range<i32>
+    where
+        Self: Sized;
     fn get_name(&self, index: i32) -> String;
     fn get_unit(&self, index: i32) -> String;
     fn is_button(&self, index: i32) -> bool;
     fn is_checkbox(&self, index: i32) -> bool;
     fn get_range(&self, index: i32) -> std::ops::RangeInclusive<f32>;
+    fn get_choices(&self, index: i32) -> Vec<String>;
+    fn get_section(&self, index: i32) -> String;
     fn get_value(&self, index: i32) -> f32;
     fn get_value_text(&self, index: i32) -> String;
     fn set_value(&self, index: i32, value: f32);
+    fn set_value_text(&self, index: i32, text: &str) -> bool;
+    fn value_to_text(&self, index: i32, value: f32) -> String;
+    fn text_to_value(&self, index: i32, text: &str) -> Option<f32>;
+    fn normalize(&self, index: i32, value: f32) -> f32;
+    fn denormalize(&self, index: i32, normalized: f32) -> f32;
+    fn get_default(&self, index: i32) -> f32;
 
-    fn num_meters() -> usize;
-    fn meter_index_range() -> std::ops::Range<i32>;
+    fn num_meters() -> usize
+    where
+        Self: Sized;
+    fn meter_index_range() -> std::ops::Range<i32>
+    where
+        Self: Sized;
     fn get_meter_name(&self, index: i32) -> String;
     fn get_meter_unit(&self, index: i32) -> String;
     fn get_meter_value(&self, index: i32) -> f32;
@@ -19,3 +41,101 @@ pub trait Params {
     fn get_meter_range(&self, index: i32) -> std::ops::RangeInclusive<f32>;
     fn get_meter_group(&self, index: i32) -> String;
 }
+
+/// An enum selected by a `kind = "choice"` param, which stores the variant index.
+pub trait Choice: Sized {
+    /// The variants as shown to the user, in index order.
+    const NAMES: &'static [&'static str];
+
+    fn from_index(index: usize) -> Self;
+
+    /// Reads the variant from the value of a choice param.
+    fn from_value(value: f32) -> Self {
+        Self::from_index(value.max(0.0).round() as usize)
+    }
+}
+
+/// Formats a value with its unit as shown to the user, by the host and the editor
+/// alike. Silence reads "-∞ dB", and ms from 1000 up read as seconds.
+pub fn format_value(value: f32, unit: &str, precision: usize) -> String {
+    let (value, unit, precision) = if unit == "ms" && value.abs() >= 1000.0 {
+        (value / 1000.0, "s", precision + 1)
+    } else {
+        (value, unit, precision)
+    };
+
+    let number = if value == f32::NEG_INFINITY {
+        "-∞".to_string()
+    } else if value == f32::INFINITY {
+        "∞".to_string()
+    } else {
+        let number = format!("{:.*}", precision, value);
+
+        // A small negative value rounded to zero would read "-0.0".
+        if number.starts_with('-') && number.chars().all(|c| c == '-' || c == '0' || c == '.') {
+            number[1..].to_string()
+        } else {
+            number
+        }
+    };
+
+    if unit.is_empty() {
+        number
+    } else {
+        format!("{} {}", number, unit)
+    }
+}
+
+/// Clamps a value read back from bank data or typed in to the range of its param,
+/// so neither can set one the plugin is not built for. -∞ stays in the level units,
+/// where it is silence.
+pub fn clamp_restored_value(value: f32, range: std::ops::RangeInclusive<f32>, unit: &str) -> f32 {
+    let is_level = matches!(unit, "dB" | "dBFS" | "dBTP" | "LKFS");
+
+    if is_level && value == f32::NEG_INFINITY {
+        value
+    } else {
+        value.max(*range.start()).min(*range.end())
+    }
+}
+
+/// Parses text typed into a host's parameter field for a param shown in `unit`.
+///
+/// The unit may be left out or written in another accepted form: "1.5s" for a
+/// ms param, "-23 LUFS" for an LKFS one, "-6dB" for any dB kind. Toggles also take
+/// "on" and "off".
+pub fn parse_value_text(text: &str, unit: &str, is_toggle: bool) -> Option<f32> {
+    let text = text.trim().to_lowercase().replace('∞', "inf");
+
+    if is_toggle {
+        match text.as_str() {
+            "on" | "true" | "yes" => return Some(1.0),
+            "off" | "false" | "no" => return Some(0.0),
+            _ => {}
+        }
+    }
+
+    // Suffixes accepted for each unit, with the factor bringing them into it.
+    // Longer suffixes come first so "ms" is not taken for "s".
+    let suffixes: &[(&str, f32)] = match unit {
+        "ms" => &[("ms", 1.0), ("s", 1000.0)],
+        "dB" | "dBFS" | "dBTP" => &[("dbfs", 1.0), ("dbtp", 1.0), ("db", 1.0)],
+        "LKFS" => &[("lkfs", 1.0), ("lufs", 1.0)],
+        "LU" => &[("lu", 1.0)],
+        "samples" => &[("samples", 1.0), ("sample", 1.0)],
+        _ => &[],
+    };
+
+    let (number, factor) = suffixes
+        .iter()
+        .find_map(|&(suffix, factor)| text.strip_suffix(suffix).map(|number| (number, factor)))
+        .unwrap_or((text.as_str(), 1.0));
+
+    let value = number.trim().parse::<f32>().ok()? * factor;
+
+    if value.is_nan() {
+        None
+    } else {
+        Some(value)
+    }
+}
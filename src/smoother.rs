@@ -0,0 +1,166 @@
+/// How a `Smoother` moves toward its target.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Ramp {
+    /// A straight line reaching the target exactly after the smoothing time.
+    Linear,
+    /// An exponential approach, with the smoothing time as its time constant.
+    OnePole,
+}
+
+/// Turns step changes of a parameter into a per-sample ramp, so automation does
+/// not click.
+pub struct Smoother {
+    ramp: Ramp,
+    samples_num_per_ramp: usize,
+
+    current: f32,
+    target: f32,
+
+    // Linear ramp: the per-sample step and how many steps are left.
+    step: f32,
+    steps_num_left: usize,
+
+    // One-pole ramp.
+    coefficient: f32,
+}
+
+impl Smoother {
+    pub fn new(ramp: Ramp, sample_rate_hz: f32, smoothing_ms: f32, value: f32) -> Self {
+        let samples_num_per_ramp = (smoothing_ms / 1000.0 * sample_rate_hz) as usize;
+
+        let coefficient = if samples_num_per_ramp > 0 {
+            (-1.0 / samples_num_per_ramp as f32).exp()
+        } else {
+            0.0
+        };
+
+        Self {
+            ramp,
+            samples_num_per_ramp,
+
+            current: value,
+            target: value,
+
+            step: 0.0,
+            steps_num_left: 0,
+
+            coefficient,
+        }
+    }
+
+    pub fn set_target(&mut self, target: f32) {
+        if target == self.target {
+            return;
+        }
+
+        self.target = target;
+
+        // A value that cannot be ramped to (e.g. from or to an infinity) is jumped to.
+        if self.samples_num_per_ramp == 0 || !(target - self.current).is_finite() {
+            self.reset(target);
+            return;
+        }
+
+        self.steps_num_left = self.samples_num_per_ramp;
+        self.step = (target - self.current) / self.samples_num_per_ramp as f32;
+    }
+
+    /// Advances by one sample and returns the smoothed value.
+    #[inline(always)]
+    pub fn next_value(&mut self) -> f32 {
+        match self.ramp {
+            Ramp::Linear => {
+                if self.steps_num_left > 0 {
+                    self.steps_num_left -= 1;
+                    self.current = if self.steps_num_left == 0 {
+                        self.target
+                    } else {
+                        self.current + self.step
+                    };
+                }
+            }
+
+            Ramp::OnePole => {
+                self.current = self.target + (self.current - self.target) * self.coefficient;
+            }
+        }
+
+        self.current
+    }
+
+    /// Returns the value last returned by `next_value`, without advancing.
+    pub fn current(&self) -> f32 {
+        self.current
+    }
+
+    /// Jumps to the value, dropping any ramp in progress.
+    pub fn reset(&mut self, value: f32) {
+        self.current = value;
+        self.target = value;
+        self.steps_num_left = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Ramp, Smoother};
+
+    #[test]
+    fn linear_ramp_reaches_the_target_in_time() {
+        // 1 ms at 48 kHz is 48 samples.
+        let mut smoother = Smoother::new(Ramp::Linear, 48000.0, 1.0, 0.0);
+        smoother.set_target(1.0);
+
+        let values: Vec<f32> = (0..48).map(|_| smoother.next_value()).collect();
+        assert!((values[0] - 1.0 / 48.0).abs() < 1e-6);
+        assert!((values[23] - 0.5).abs() < 1e-5);
+        assert_eq!(values[47], 1.0);
+        assert!(values.windows(2).all(|pair| pair[0] < pair[1]));
+
+        assert_eq!(smoother.next_value(), 1.0);
+    }
+
+    #[test]
+    fn one_pole_ramp_approaches_the_target() {
+        let mut smoother = Smoother::new(Ramp::OnePole, 48000.0, 1.0, 0.0);
+        smoother.set_target(1.0);
+
+        // After one time constant it has covered 1 - 1/e of the way.
+        let mut value = 0.0;
+        for _ in 0..48 {
+            value = smoother.next_value();
+        }
+        assert!((value - (1.0 - (-1.0f32).exp())).abs() < 1e-3);
+
+        for _ in 0..48 * 20 {
+            value = smoother.next_value();
+        }
+        assert!((value - 1.0).abs() < 1e-5, "{}", value);
+    }
+
+    #[test]
+    fn retargeting_mid_ramp_starts_from_the_current_value() {
+        let mut smoother = Smoother::new(Ramp::Linear, 48000.0, 1.0, 0.0);
+        smoother.set_target(1.0);
+        for _ in 0..24 {
+            smoother.next_value();
+        }
+
+        smoother.set_target(0.0);
+        let first = smoother.next_value();
+        assert!(first < 0.5 && first > 0.48, "{}", first);
+
+        for _ in 0..47 {
+            smoother.next_value();
+        }
+        assert_eq!(smoother.current(), 0.0);
+    }
+
+    #[test]
+    fn zero_time_jumps() {
+        let mut smoother = Smoother::new(Ramp::Linear, 48000.0, 0.0, 0.0);
+        smoother.set_target(0.5);
+
+        assert_eq!(smoother.next_value(), 0.5);
+    }
+}
@@ -0,0 +1,41 @@
+use std::ops::Range;
+
+/// How many samples the plugins process between two reads of their params.
+///
+/// VST2 hosts call `set_parameter` between blocks without a sample offset, so
+/// automation cannot be placed exactly; re-reading the params this often keeps it
+/// from being quantised to the host's block size.
+pub const SUB_BLOCK_SAMPLES_NUM: usize = 32;
+
+/// Splits a block of `samples_num` samples into consecutive ranges of at most
+/// `sub_block_samples_num` samples each.
+pub fn sub_blocks(
+    samples_num: usize,
+    sub_block_samples_num: usize,
+) -> impl Iterator<Item = Range<usize>> {
+    let sub_block_samples_num = sub_block_samples_num.max(1);
+
+    (0..samples_num)
+        .step_by(sub_block_samples_num)
+        .map(move |start| start..(start + sub_block_samples_num).min(samples_num))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sub_blocks;
+
+    #[test]
+    fn splits_into_sub_blocks() {
+        assert_eq!(
+            sub_blocks(100, 32).collect::<Vec<_>>(),
+            vec![0..32, 32..64, 64..96, 96..100]
+        );
+        assert_eq!(sub_blocks(64, 32).collect::<Vec<_>>(), vec![0..32, 32..64]);
+        assert_eq!(sub_blocks(10, 32).collect::<Vec<_>>(), vec![0..10]);
+    }
+
+    #[test]
+    fn empty_block() {
+        assert_eq!(sub_blocks(0, 32).count(), 0);
+    }
+}
@@ -55,6 +55,20 @@ impl SumBuffer {
         current_sum
     }
 
+    /// Empties the buffer in place, as if newly made.
+    pub fn reset(&mut self) {
+        for value_with_sum in &mut self.buffer {
+            *value_with_sum = ValueWithSum {
+                value: 0.0,
+                sum: 0.0,
+            };
+        }
+
+        self.current = 0;
+        self.prev = 0;
+        self.residue = 0.0;
+    }
+
     #[inline(always)]
     fn add_with_residue(&mut self, lhs: f32, rhs: f32) -> f32 {
         let result = lhs + (self.residue + rhs);
@@ -79,4 +93,15 @@ mod tests {
         assert_eq!(buffer.add(5.0), 3.0 + 4.0 + 5.0);
         assert_eq!(buffer.add(6.0), 4.0 + 5.0 + 6.0);
     }
+
+    #[test]
+    fn reset() {
+        let mut buffer = SumBuffer::new(3);
+        buffer.add(1.0);
+        buffer.add(2.0);
+
+        buffer.reset();
+        assert_eq!(buffer.add(3.0), 3.0);
+        assert_eq!(buffer.add(4.0), 3.0 + 4.0);
+    }
 }
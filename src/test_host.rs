@@ -0,0 +1,155 @@
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use vst::host::HostBuffer;
+use vst::plugin::{HostCallback, PluginParameters};
+
+use crate::params::Params;
+use crate::wrapped::WrappedPlugin;
+
+/// A stereo 1 kHz sine at `dbfs`, which reads the same in LKFS, lasting `seconds`.
+/// It starts at zero phase, so sines lasting whole milliseconds join up seamlessly.
+pub fn sine(sample_rate_hz: f32, dbfs: f32, seconds: f32) -> Vec<Vec<f32>> {
+    let amplitude = 10f32.powf(dbfs / 20.0);
+
+    // 1 kHz fits a whole number of periods into every second, so the phase is taken
+    // within the second to keep it precise over long signals.
+    let channel: Vec<f32> = (0..(seconds * sample_rate_hz).round() as usize)
+        .map(|n| {
+            let seconds = (n % sample_rate_hz as usize) as f32 / sample_rate_hz;
+            amplitude * (2.0 * PI * 1000.0 * seconds).sin()
+        })
+        .collect();
+
+    vec![channel.clone(), channel]
+}
+
+/// The highest sample magnitude over all of `channels`.
+pub fn peak(channels: &[Vec<f32>]) -> f32 {
+    channels
+        .iter()
+        .flatten()
+        .fold(0.0f32, |peak, sample| peak.max(sample.abs()))
+}
+
+/// Runs a plugin in process for end-to-end tests: blocks of any size go through
+/// `Plugin::process`, and params are set through `PluginParameters` as a VST2 host
+/// sets them.
+pub struct TestHost<P: WrappedPlugin> {
+    plugin: P,
+    params: Arc<P::Params>,
+    parameters: Arc<dyn PluginParameters>,
+    channels_num: usize,
+    host_buffer: HostBuffer<f32>,
+}
+
+impl<P: WrappedPlugin> TestHost<P> {
+    /// A resumed instance at `sample_rate_hz`, fed `channels_num` channels. No VST2
+    /// host is behind it, so latency is only read through `latency_samples`.
+    pub fn new(sample_rate_hz: f32, channels_num: usize) -> Self {
+        let mut plugin = P::new(HostCallback::default());
+        plugin.init();
+        plugin.set_sample_rate(sample_rate_hz);
+        plugin.resume();
+
+        let params = plugin.params();
+        let parameters = plugin.get_parameter_object();
+
+        Self {
+            plugin,
+            params,
+            parameters,
+            channels_num,
+            host_buffer: HostBuffer::new(channels_num, channels_num),
+        }
+    }
+
+    pub fn plugin(&mut self) -> &mut P {
+        &mut self.plugin
+    }
+
+    pub fn params(&self) -> &P::Params {
+        &self.params
+    }
+
+    /// Sets the param named `name` from a normalized value, as host automation does.
+    /// Panics if there is no such param.
+    pub fn set_parameter(&self, name: &str, value: f32) {
+        self.parameters
+            .set_parameter(self.parameter_index(name), value);
+    }
+
+    /// Sets the param named `name` from text as typed into the host, e.g. "-16" or
+    /// "1.5s". Panics if there is no such param or the text is rejected.
+    pub fn set_parameter_text(&self, name: &str, text: &str) {
+        let index = self.parameter_index(name);
+
+        assert!(
+            self.parameters.string_to_parameter(index, text.to_string()),
+            "invalid value {} for {}",
+            text,
+            name
+        );
+    }
+
+    /// The normalized value of the param named `name`, as the host reads it.
+    pub fn parameter(&self, name: &str) -> f32 {
+        self.parameters.get_parameter(self.parameter_index(name))
+    }
+
+    /// Runs one block, one buffer per channel, all of the same length, and returns
+    /// the output buffers.
+    pub fn process(&mut self, inputs: &[Vec<f32>]) -> Vec<Vec<f32>> {
+        assert_eq!(inputs.len(), self.channels_num);
+
+        let samples_num = inputs.first().map_or(0, |input| input.len());
+        assert!(inputs.iter().all(|input| input.len() == samples_num));
+
+        let mut outputs = vec![vec![0.0; samples_num]; self.channels_num];
+        let mut buffer = self.host_buffer.bind(inputs, &mut outputs);
+        self.plugin.process(&mut buffer);
+
+        outputs
+    }
+
+    /// Runs `inputs` through in consecutive blocks of `block_size` samples, the last
+    /// one possibly shorter, and returns the output joined back together.
+    pub fn process_in_blocks(&mut self, inputs: &[Vec<f32>], block_size: usize) -> Vec<Vec<f32>> {
+        let samples_num = inputs.first().map_or(0, |input| input.len());
+        let mut outputs = vec![Vec::with_capacity(samples_num); self.channels_num];
+
+        for start in (0..samples_num).step_by(block_size.max(1)) {
+            let end = (start + block_size).min(samples_num);
+            let block: Vec<Vec<f32>> = inputs
+                .iter()
+                .map(|input| input[start..end].to_vec())
+                .collect();
+
+            for (output, block) in outputs.iter_mut().zip(self.process(&block)) {
+                output.extend(block);
+            }
+        }
+
+        outputs
+    }
+
+    /// The value of the meter named `name` in its unit, as of the last block. Panics
+    /// if there is no such meter.
+    pub fn meter(&self, name: &str) -> f32 {
+        let index = P::Params::meter_index_range()
+            .find(|&index| self.params.get_meter_name(index) == name)
+            .unwrap_or_else(|| panic!("unknown meter {}", name));
+
+        self.params.get_meter_value(index)
+    }
+
+    pub fn latency_samples(&self) -> u32 {
+        self.plugin.latency_samples()
+    }
+
+    fn parameter_index(&self, name: &str) -> i32 {
+        P::Params::index_range()
+            .find(|&index| self.params.get_name(index) == name)
+            .unwrap_or_else(|| panic!("unknown param {}", name))
+    }
+}
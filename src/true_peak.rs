@@ -0,0 +1,168 @@
+const PHASES_NUM: usize = 4;
+const TAPS_NUM_PER_PHASE: usize = 12;
+
+// Coefficients of the 48-tap interpolation filter for 4x oversampling, split into
+// its 4 phases, taken from BS.1770-4 Annex 2.
+#[allow(clippy::excessive_precision)]
+const COEFFICIENTS: [[f32; TAPS_NUM_PER_PHASE]; PHASES_NUM] = [
+    [
+        0.0017089843750,
+        0.0109863281250,
+        -0.0196533203125,
+        0.0332031250000,
+        -0.0594482421875,
+        0.1373291015625,
+        0.9721679687500,
+        -0.1022949218750,
+        0.0476074218750,
+        -0.0266113281250,
+        0.0148925781250,
+        -0.0083007812500,
+    ],
+    [
+        -0.0291748046875,
+        0.0292968750000,
+        -0.0517578125000,
+        0.0891113281250,
+        -0.1665039062500,
+        0.4650878906250,
+        0.7797851562500,
+        -0.2003173828125,
+        0.1015625000000,
+        -0.0582275390625,
+        0.0330810546875,
+        -0.0189208984375,
+    ],
+    [
+        -0.0189208984375,
+        0.0330810546875,
+        -0.0582275390625,
+        0.1015625000000,
+        -0.2003173828125,
+        0.7797851562500,
+        0.4650878906250,
+        -0.1665039062500,
+        0.0891113281250,
+        -0.0517578125000,
+        0.0292968750000,
+        -0.0291748046875,
+    ],
+    [
+        -0.0083007812500,
+        0.0148925781250,
+        -0.0266113281250,
+        0.0476074218750,
+        -0.1022949218750,
+        0.9721679687500,
+        0.1373291015625,
+        -0.0594482421875,
+        0.0332031250000,
+        -0.0196533203125,
+        0.0109863281250,
+        0.0017089843750,
+    ],
+];
+
+pub struct TruePeak {
+    interpolators: Vec<Interpolator>,
+}
+
+impl TruePeak {
+    pub fn new(channels_num: usize) -> TruePeak {
+        TruePeak {
+            interpolators: (0..channels_num).map(|_| Interpolator::new()).collect(),
+        }
+    }
+
+    /// Takes one sample per channel and returns the largest absolute value among the
+    /// oversampled samples of all channels. The interpolation filter delays it by
+    /// half its length (6 samples).
+    #[inline(always)]
+    pub fn add_samples(&mut self, samples: &[f32]) -> f32 {
+        self.interpolators
+            .iter_mut()
+            .zip(samples)
+            .map(|(interpolator, sample)| interpolator.peak(*sample))
+            .fold(0.0, f32::max)
+    }
+
+    pub fn reset(&mut self) {
+        for interpolator in &mut self.interpolators {
+            *interpolator = Interpolator::new();
+        }
+    }
+}
+
+struct Interpolator {
+    // The past input samples, newest first.
+    history: [f32; TAPS_NUM_PER_PHASE],
+}
+
+impl Interpolator {
+    fn new() -> Interpolator {
+        Interpolator {
+            history: [0.0; TAPS_NUM_PER_PHASE],
+        }
+    }
+
+    #[inline(always)]
+    fn peak(&mut self, sample: f32) -> f32 {
+        self.history.copy_within(0..TAPS_NUM_PER_PHASE - 1, 1);
+        self.history[0] = sample;
+
+        let mut peak = 0.0f32;
+        for coefficients in &COEFFICIENTS {
+            let value: f32 = coefficients
+                .iter()
+                .zip(&self.history)
+                .map(|(coefficient, sample)| coefficient * sample)
+                .sum();
+
+            peak = peak.max(value.abs());
+        }
+
+        peak
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TruePeak;
+    use std::f32::consts::PI;
+
+    fn max_true_peak_dbtp(frequency_hz: f32, phase: f32) -> f32 {
+        let sample_rate_hz = 48000.0;
+        let mut true_peak = TruePeak::new(2);
+
+        let mut max_peak = 0.0f32;
+        for n in 0..sample_rate_hz as usize {
+            let sample = (2.0 * PI * frequency_hz * n as f32 / sample_rate_hz + phase).sin();
+            max_peak = max_peak.max(true_peak.add_samples(&[sample, 0.0]));
+        }
+
+        20.0 * max_peak.log10()
+    }
+
+    #[test]
+    fn inter_sample_peak() {
+        // Sampled 45 degrees off its crests, a fs/4 sine never exceeds -3.01 dBFS in
+        // its samples but still peaks at 0 dBTP in between.
+        let dbtp = max_true_peak_dbtp(12000.0, PI / 4.0);
+        assert!((dbtp - 0.0).abs() < 0.3, "{} dBTP", dbtp);
+    }
+
+    #[test]
+    fn peak_on_a_sample() {
+        let dbtp = max_true_peak_dbtp(1000.0, 0.0);
+        assert!((dbtp - 0.0).abs() < 0.3, "{} dBTP", dbtp);
+    }
+
+    #[test]
+    fn silence() {
+        let mut true_peak = TruePeak::new(2);
+
+        for _ in 0..100 {
+            assert_eq!(true_peak.add_samples(&[0.0, 0.0]), 0.0);
+        }
+    }
+}
@@ -0,0 +1,921 @@
+use std::cell::RefCell;
+use std::ffi::CStr;
+use std::iter;
+use std::os::raw::c_void;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use vst::buffer::AudioBuffer;
+use vst::editor::Editor as VstEditor;
+use vst::plugin::{HostCallback, PluginParameters};
+use vst3_sys::base::{
+    char16, kInvalidArgument, kNoInterface, kNotImplemented, kResultFalse, kResultOk, kResultTrue,
+    tresult, ClassCardinality, FIDString, IBStream, IPluginBase, IPluginFactory, IUnknown,
+    PClassInfo, PFactoryInfo, TBool,
+};
+use vst3_sys::gui::{IPlugView, ViewRect};
+use vst3_sys::utils::SharedVstPtr;
+use vst3_sys::vst::{
+    AudioBusBuffers, BusDirection, BusDirections, BusFlags, BusInfo, BusTypes, IAudioProcessor,
+    IComponent, IComponentHandler, IEditController, IoMode, MediaType, MediaTypes, ParameterFlags,
+    ParameterInfo, ProcessData, ProcessSetup, RestartFlags, RoutingInfo, SpeakerArrangement,
+    SymbolicSampleSizes, TChar,
+};
+use vst3_sys::{VstPtr, IID, VST3};
+
+use crate::channels::CHANNELS_NUM;
+use crate::params::Params;
+use crate::wrapped::{copy_cstr, Instance, Wrapped, WrappedPlugin};
+
+const VENDOR: &str = "youxkei";
+const URL: &str = "https://github.com/youxkei/jimtel";
+
+// Each bus starts out as stereo; hosts may ask for 5.1 or 7.1 instead. These are
+// the layouts whose channels come in the order of `jimtel::channels`, which the
+// loudness weights assume: L R C LFE Ls Rs, then Lc Rc or Sl Sr.
+const STEREO: SpeakerArrangement = 0b11;
+const SURROUND_5_1: SpeakerArrangement = 0b11_1111;
+const SURROUND_7_1_CINE: SpeakerArrangement = 0b1111_1111;
+const SURROUND_7_1_MUSIC: SpeakerArrangement = 0b110_0011_1111;
+// Automation points kept per block, so `changes` never grows on the audio thread.
+const MAX_CHANGES_NUM: usize = 1024;
+
+const ARRANGEMENTS: [SpeakerArrangement; 4] =
+    [STEREO, SURROUND_5_1, SURROUND_7_1_CINE, SURROUND_7_1_MUSIC];
+
+#[cfg(target_os = "linux")]
+const PLATFORM_TYPE: &[u8] = b"X11EmbedWindowID";
+#[cfg(target_os = "windows")]
+const PLATFORM_TYPE: &[u8] = b"HWND";
+#[cfg(target_os = "macos")]
+const PLATFORM_TYPE: &[u8] = b"NSView";
+
+// What the audio thread owns, behind one lock.
+struct Processor {
+    instance: Box<dyn Instance>,
+
+    // Param changes of the current block as (sample offset, index, normalized value),
+    // up to `MAX_CHANGES_NUM`.
+    changes: Vec<(usize, i32, f32)>,
+
+    // Channel pointers of the current sub-block.
+    inputs: Vec<*const f32>,
+    outputs: Vec<*mut f32>,
+}
+
+// The processor and the edit controller in one class, as the VST3 SDK's
+// `SingleComponentEffect`. The params are shared with the plugin, so both sides
+// always agree on them.
+#[VST3(implements(IComponent, IAudioProcessor, IEditController))]
+pub struct Component {
+    processor: Mutex<Processor>,
+    params: Arc<dyn Params + Send + Sync>,
+    parameters: Arc<dyn PluginParameters>,
+    params_num: i32,
+
+    input_arrangement: AtomicU64,
+    output_arrangement: AtomicU64,
+    latency_samples: AtomicU32,
+
+    // Set by `process` when the latency changes. Hosts take `restartComponent` on
+    // the UI thread only, so it is passed on from the controller calls made there.
+    latency_changed: AtomicBool,
+    handler: Mutex<Option<VstPtr<dyn IComponentHandler>>>,
+}
+
+impl Component {
+    fn new<P: WrappedPlugin>() -> Box<Self> {
+        let wrapped = Wrapped::new::<P>();
+        let latency_samples = wrapped.instance.latency_samples();
+
+        Self::allocate(
+            Mutex::new(Processor {
+                instance: wrapped.instance,
+                changes: Vec::with_capacity(MAX_CHANGES_NUM),
+                inputs: Vec::with_capacity(CHANNELS_NUM),
+                outputs: Vec::with_capacity(CHANNELS_NUM),
+            }),
+            wrapped.params,
+            wrapped.parameters,
+            wrapped.params_num,
+            AtomicU64::new(STEREO),
+            AtomicU64::new(STEREO),
+            AtomicU32::new(latency_samples),
+            AtomicBool::new(false),
+            Mutex::new(None),
+        )
+    }
+
+    fn arrangement(&self, dir: BusDirection) -> &AtomicU64 {
+        if dir == BusDirections::kInput as i32 {
+            &self.input_arrangement
+        } else {
+            &self.output_arrangement
+        }
+    }
+
+    fn has_param(&self, id: u32) -> bool {
+        id < self.params_num as u32
+    }
+
+    // Sets each automated param to the last point of its queue in `data`.
+    unsafe fn apply_last_points(&self, data: &ProcessData) {
+        let param_changes = match data.input_param_changes.upgrade() {
+            Some(param_changes) => param_changes,
+            None => return,
+        };
+
+        for i in 0..param_changes.get_parameter_count() {
+            let queue = match param_changes.get_parameter_data(i).upgrade() {
+                Some(queue) => queue,
+                None => continue,
+            };
+
+            let id = queue.get_parameter_id();
+            let mut offset = 0;
+            let mut value = 0.0;
+            if self.has_param(id)
+                && queue.get_point(queue.get_point_count() - 1, &mut offset, &mut value)
+                    == kResultOk
+            {
+                self.parameters.set_parameter(id as i32, value as f32);
+            }
+        }
+    }
+
+    // Called on the UI thread. The handler is only tried: a host calling back into
+    // the controller from `restartComponent` finds it locked and leaves the change
+    // for the next call.
+    unsafe fn notify_latency_change(&self) {
+        if !self.latency_changed.swap(false, Ordering::Relaxed) {
+            return;
+        }
+
+        match self.handler.try_lock() {
+            Ok(handler) => {
+                if let Some(handler) = handler.as_ref() {
+                    handler.restart_component(RestartFlags::kLatencyChanged as i32);
+                }
+            }
+
+            Err(_) => self.latency_changed.store(true, Ordering::Relaxed),
+        }
+    }
+}
+
+impl IPluginBase for Component {
+    unsafe fn initialize(&self, _context: *mut c_void) -> tresult {
+        kResultOk
+    }
+
+    unsafe fn terminate(&self) -> tresult {
+        *self.handler.lock().unwrap() = None;
+        kResultOk
+    }
+}
+
+impl IComponent for Component {
+    unsafe fn get_controller_class_id(&self, _tuid: *mut IID) -> tresult {
+        // The controller is this same object.
+        kNotImplemented
+    }
+
+    unsafe fn set_io_mode(&self, _mode: IoMode) -> tresult {
+        kResultOk
+    }
+
+    unsafe fn get_bus_count(&self, type_: MediaType, _dir: BusDirection) -> i32 {
+        if type_ == MediaTypes::kAudio as i32 {
+            1
+        } else {
+            0
+        }
+    }
+
+    unsafe fn get_bus_info(
+        &self,
+        type_: MediaType,
+        dir: BusDirection,
+        index: i32,
+        info: *mut BusInfo,
+    ) -> tresult {
+        if type_ != MediaTypes::kAudio as i32 || index != 0 || info.is_null() {
+            return kInvalidArgument;
+        }
+
+        let info = &mut *info;
+        let name = if dir == BusDirections::kInput as i32 {
+            "Input"
+        } else {
+            "Output"
+        };
+
+        info.media_type = type_;
+        info.direction = dir;
+        info.channel_count = self.arrangement(dir).load(Ordering::Relaxed).count_ones() as i32;
+        copy_wstr(&mut info.name, name);
+        info.bus_type = BusTypes::kMain as i32;
+        info.flags = BusFlags::kDefaultActive as u32;
+
+        kResultOk
+    }
+
+    unsafe fn get_routing_info(
+        &self,
+        _in_info: *mut RoutingInfo,
+        _out_info: *mut RoutingInfo,
+    ) -> tresult {
+        kNotImplemented
+    }
+
+    unsafe fn activate_bus(
+        &self,
+        _type_: MediaType,
+        _dir: BusDirection,
+        _index: i32,
+        _state: TBool,
+    ) -> tresult {
+        kResultOk
+    }
+
+    unsafe fn set_active(&self, state: TBool) -> tresult {
+        let mut processor = self.processor.lock().unwrap();
+
+        if state != 0 {
+            processor.instance.plugin().resume();
+        } else {
+            processor.instance.plugin().suspend();
+        }
+        drop(processor);
+
+        self.notify_latency_change();
+
+        kResultOk
+    }
+
+    // The component state is the bank data of the VST2 build, so sessions can
+    // move between the formats.
+    unsafe fn set_state(&self, state: SharedVstPtr<dyn IBStream>) -> tresult {
+        let state = match state.upgrade() {
+            Some(state) => state,
+            None => return kInvalidArgument,
+        };
+
+        let mut data = Vec::new();
+        let mut chunk = [0u8; 4096];
+        loop {
+            let mut read = 0;
+            let result = state.read(
+                chunk.as_mut_ptr() as *mut c_void,
+                chunk.len() as i32,
+                &mut read,
+            );
+            if result != kResultOk || read <= 0 {
+                break;
+            }
+
+            data.extend_from_slice(&chunk[..read as usize]);
+        }
+
+        self.parameters.load_bank_data(&data);
+
+        kResultOk
+    }
+
+    unsafe fn get_state(&self, state: SharedVstPtr<dyn IBStream>) -> tresult {
+        let state = match state.upgrade() {
+            Some(state) => state,
+            None => return kInvalidArgument,
+        };
+
+        let data = self.parameters.get_bank_data();
+        let mut written = 0;
+        state.write(
+            data.as_ptr() as *mut c_void,
+            data.len() as i32,
+            &mut written,
+        )
+    }
+}
+
+impl IAudioProcessor for Component {
+    unsafe fn set_bus_arrangements(
+        &self,
+        inputs: *mut SpeakerArrangement,
+        num_ins: i32,
+        outputs: *mut SpeakerArrangement,
+        num_outs: i32,
+    ) -> tresult {
+        if num_ins != 1 || num_outs != 1 || inputs.is_null() || outputs.is_null() {
+            return kResultFalse;
+        }
+
+        // The plugins weight the channels they are given by their position in 7.1,
+        // so only layouts in that order are taken, the same on both sides.
+        let (input, output) = (*inputs, *outputs);
+        if input != output || !ARRANGEMENTS.contains(&input) {
+            return kResultFalse;
+        }
+
+        self.input_arrangement.store(input, Ordering::Relaxed);
+        self.output_arrangement.store(output, Ordering::Relaxed);
+
+        kResultTrue
+    }
+
+    unsafe fn get_bus_arrangement(
+        &self,
+        dir: BusDirection,
+        index: i32,
+        arr: *mut SpeakerArrangement,
+    ) -> tresult {
+        if index != 0 || arr.is_null() {
+            return kInvalidArgument;
+        }
+
+        *arr = self.arrangement(dir).load(Ordering::Relaxed);
+
+        kResultOk
+    }
+
+    unsafe fn can_process_sample_size(&self, symbolic_sample_size: i32) -> tresult {
+        if symbolic_sample_size == SymbolicSampleSizes::kSample32 as i32 {
+            kResultTrue
+        } else {
+            kResultFalse
+        }
+    }
+
+    unsafe fn get_latency_samples(&self) -> u32 {
+        self.latency_samples.load(Ordering::Relaxed)
+    }
+
+    unsafe fn setup_processing(&self, setup: *const ProcessSetup) -> tresult {
+        if setup.is_null() {
+            return kInvalidArgument;
+        }
+
+        let setup = &*setup;
+        let mut processor = self.processor.lock().unwrap();
+        let plugin = processor.instance.plugin();
+
+        plugin.set_sample_rate(setup.sample_rate as f32);
+        plugin.set_block_size(setup.max_samples_per_block as i64);
+
+        let latency_samples = processor.instance.latency_samples();
+        self.latency_samples
+            .store(latency_samples, Ordering::Relaxed);
+
+        kResultOk
+    }
+
+    unsafe fn set_processing(&self, _state: TBool) -> tresult {
+        kResultOk
+    }
+
+    unsafe fn process(&self, data: *mut ProcessData) -> tresult {
+        if data.is_null() {
+            return kInvalidArgument;
+        }
+
+        let data = &*data;
+        let samples_num = data.num_samples.max(0) as usize;
+        let (inputs, inputs_num) = bus_channels(data.inputs, data.num_inputs);
+        let (outputs, outputs_num) = bus_channels(data.outputs, data.num_outputs);
+
+        // The UI thread holds the lock only briefly, e.g. to create the editor. A
+        // block that meets it comes out silent, rather than waiting for it or
+        // passing the input through unlimited.
+        let mut processor = match self.processor.try_lock() {
+            Ok(processor) => processor,
+            Err(_) => {
+                for channel in 0..outputs_num {
+                    let output = *outputs.add(channel) as *mut f32;
+                    std::slice::from_raw_parts_mut(output, samples_num).fill(0.0);
+                }
+                self.apply_last_points(data);
+
+                return kResultOk;
+            }
+        };
+        let processor = &mut *processor;
+
+        // Automation comes as points with sample offsets. The block is split at each
+        // of them, on top of the plugins' own sub-blocks. Points past the capacity
+        // of `changes` are dropped, and their params jump to their last value at
+        // the end of the block.
+        processor.changes.clear();
+        let mut points_dropped = false;
+        if let Some(param_changes) = data.input_param_changes.upgrade() {
+            for i in 0..param_changes.get_parameter_count() {
+                let queue = match param_changes.get_parameter_data(i).upgrade() {
+                    Some(queue) => queue,
+                    None => continue,
+                };
+
+                let id = queue.get_parameter_id();
+                if !self.has_param(id) {
+                    continue;
+                }
+
+                for j in 0..queue.get_point_count() {
+                    if processor.changes.len() == MAX_CHANGES_NUM {
+                        points_dropped = true;
+                        break;
+                    }
+
+                    let mut offset = 0;
+                    let mut value = 0.0;
+                    if queue.get_point(j, &mut offset, &mut value) == kResultOk {
+                        let offset = (offset.max(0) as usize).min(samples_num);
+                        processor.changes.push((offset, id as i32, value as f32));
+                    }
+                }
+            }
+        }
+        processor.changes.sort_by_key(|&(offset, _, _)| offset);
+
+        let mut start = 0;
+        let mut change = 0;
+        while start < samples_num {
+            while let Some(&(offset, index, value)) = processor.changes.get(change) {
+                if offset > start {
+                    break;
+                }
+
+                self.parameters.set_parameter(index, value);
+                change += 1;
+            }
+
+            let end = processor
+                .changes
+                .get(change)
+                .map_or(samples_num, |&(offset, _, _)| offset);
+
+            processor.inputs.clear();
+            processor.outputs.clear();
+            for channel in 0..inputs_num {
+                processor
+                    .inputs
+                    .push((*inputs.add(channel) as *const f32).add(start));
+            }
+            for channel in 0..outputs_num {
+                processor
+                    .outputs
+                    .push((*outputs.add(channel) as *mut f32).add(start));
+            }
+
+            let mut buffer = AudioBuffer::from_raw(
+                inputs_num,
+                outputs_num,
+                processor.inputs.as_ptr(),
+                processor.outputs.as_mut_ptr(),
+                end - start,
+            );
+            processor.instance.plugin().process(&mut buffer);
+
+            start = end;
+        }
+
+        // Points at the very end of the block, or all of them when the host only
+        // flushes params with an empty block.
+        for &(_, index, value) in &processor.changes[change..] {
+            self.parameters.set_parameter(index, value);
+        }
+        if points_dropped {
+            self.apply_last_points(data);
+        }
+
+        let latency_samples = processor.instance.latency_samples();
+        if self
+            .latency_samples
+            .swap(latency_samples, Ordering::Relaxed)
+            != latency_samples
+        {
+            self.latency_changed.store(true, Ordering::Relaxed);
+        }
+
+        kResultOk
+    }
+
+    unsafe fn get_tail_samples(&self) -> u32 {
+        0
+    }
+}
+
+impl IEditController for Component {
+    unsafe fn set_component_state(&self, _state: SharedVstPtr<dyn IBStream>) -> tresult {
+        // Already applied by `IComponent::set_state`, on the same params.
+        kResultOk
+    }
+
+    unsafe fn set_state(&self, _state: SharedVstPtr<dyn IBStream>) -> tresult {
+        kResultOk
+    }
+
+    unsafe fn get_state(&self, _state: SharedVstPtr<dyn IBStream>) -> tresult {
+        kResultOk
+    }
+
+    unsafe fn get_parameter_count(&self) -> i32 {
+        self.params_num
+    }
+
+    unsafe fn get_parameter_info(&self, param_index: i32, info: *mut ParameterInfo) -> tresult {
+        if param_index < 0 || param_index >= self.params_num || info.is_null() {
+            return kInvalidArgument;
+        }
+
+        let info = &mut *info;
+        let index = param_index;
+        let choices_num = self.params.get_choices(index).len() as i32;
+
+        info.id = index as u32;
+        copy_wstr(&mut info.title, &self.params.get_name(index));
+        copy_wstr(&mut info.short_title, &self.params.get_name(index));
+        copy_wstr(&mut info.units, &self.params.get_unit(index));
+        info.step_count = if choices_num > 0 {
+            choices_num - 1
+        } else if self.params.is_button(index) || self.params.is_checkbox(index) {
+            1
+        } else {
+            0
+        };
+        info.default_normalized_value =
+            self.params.normalize(index, self.params.get_default(index)) as f64;
+        info.unit_id = 0;
+        info.flags = if choices_num > 0 {
+            ParameterFlags::kCanAutomate as i32 | ParameterFlags::kIsList as i32
+        } else {
+            ParameterFlags::kCanAutomate as i32
+        };
+
+        kResultOk
+    }
+
+    unsafe fn get_param_string_by_value(
+        &self,
+        id: u32,
+        value_normalized: f64,
+        string: *mut TChar,
+    ) -> tresult {
+        if !self.has_param(id) || string.is_null() {
+            return kInvalidArgument;
+        }
+
+        let index = id as i32;
+        let value = self.params.denormalize(index, value_normalized as f32);
+        let text = self.params.value_to_text(index, value);
+
+        copy_wstr(std::slice::from_raw_parts_mut(string, 128), &text);
+
+        kResultOk
+    }
+
+    unsafe fn get_param_value_by_string(
+        &self,
+        id: u32,
+        string: *const TChar,
+        value_normalized: *mut f64,
+    ) -> tresult {
+        if !self.has_param(id) || string.is_null() || value_normalized.is_null() {
+            return kInvalidArgument;
+        }
+
+        let index = id as i32;
+        match self.params.text_to_value(index, &read_wstr(string)) {
+            Some(value) => {
+                *value_normalized = self.params.normalize(index, value) as f64;
+                kResultOk
+            }
+
+            None => kResultFalse,
+        }
+    }
+
+    unsafe fn normalized_param_to_plain(&self, id: u32, value_normalized: f64) -> f64 {
+        if !self.has_param(id) {
+            return value_normalized;
+        }
+
+        self.params.denormalize(id as i32, value_normalized as f32) as f64
+    }
+
+    unsafe fn plain_param_to_normalized(&self, id: u32, plain_value: f64) -> f64 {
+        if !self.has_param(id) {
+            return plain_value;
+        }
+
+        self.params.normalize(id as i32, plain_value as f32) as f64
+    }
+
+    // Hosts poll this to keep their controls up to date.
+    unsafe fn get_param_normalized(&self, id: u32) -> f64 {
+        self.notify_latency_change();
+
+        self.parameters.get_parameter(id as i32) as f64
+    }
+
+    unsafe fn set_param_normalized(&self, id: u32, value: f64) -> tresult {
+        if !self.has_param(id) {
+            return kInvalidArgument;
+        }
+
+        self.parameters.set_parameter(id as i32, value as f32);
+        self.notify_latency_change();
+
+        kResultOk
+    }
+
+    unsafe fn set_component_handler(
+        &self,
+        handler: SharedVstPtr<dyn IComponentHandler>,
+    ) -> tresult {
+        *self.handler.lock().unwrap() = handler.upgrade();
+        self.notify_latency_change();
+
+        kResultOk
+    }
+
+    unsafe fn create_view(&self, name: FIDString) -> *mut c_void {
+        if name.is_null() || CStr::from_ptr(name).to_bytes() != b"editor" {
+            return ptr::null_mut();
+        }
+
+        // Held only to create the editor; `process` skips a block meanwhile rather
+        // than wait.
+        let editor = self
+            .processor
+            .lock()
+            .unwrap()
+            .instance
+            .plugin()
+            .get_editor();
+
+        match editor {
+            Some(editor) => Box::into_raw(View::new(editor)) as *mut c_void,
+            None => ptr::null_mut(),
+        }
+    }
+}
+
+// Hosts the `jimtel::editor::Editor` (or any VST2 editor) in the window the host
+// attaches it to.
+#[VST3(implements(IPlugView))]
+pub struct View {
+    editor: RefCell<Box<dyn VstEditor>>,
+}
+
+impl View {
+    fn new(editor: Box<dyn VstEditor>) -> Box<Self> {
+        Self::allocate(RefCell::new(editor))
+    }
+}
+
+impl IPlugView for View {
+    unsafe fn is_platform_type_supported(&self, type_: FIDString) -> tresult {
+        if !type_.is_null() && CStr::from_ptr(type_).to_bytes() == PLATFORM_TYPE {
+            kResultTrue
+        } else {
+            kResultFalse
+        }
+    }
+
+    unsafe fn attached(&self, parent: *mut c_void, type_: FIDString) -> tresult {
+        if self.is_platform_type_supported(type_) != kResultTrue {
+            return kResultFalse;
+        }
+
+        if self.editor.borrow_mut().open(parent) {
+            kResultOk
+        } else {
+            kResultFalse
+        }
+    }
+
+    unsafe fn removed(&self) -> tresult {
+        self.editor.borrow_mut().close();
+
+        kResultOk
+    }
+
+    unsafe fn on_wheel(&self, _distance: f32) -> tresult {
+        kResultFalse
+    }
+
+    unsafe fn on_key_down(&self, _key: char16, _key_code: i16, _modifiers: i16) -> tresult {
+        kResultFalse
+    }
+
+    unsafe fn on_key_up(&self, _key: char16, _key_code: i16, _modifiers: i16) -> tresult {
+        kResultFalse
+    }
+
+    unsafe fn get_size(&self, size: *mut ViewRect) -> tresult {
+        if size.is_null() {
+            return kInvalidArgument;
+        }
+
+        let (width, height) = self.editor.borrow().size();
+        let size = &mut *size;
+        size.left = 0;
+        size.top = 0;
+        size.right = width;
+        size.bottom = height;
+
+        kResultOk
+    }
+
+    unsafe fn on_size(&self, _new_size: *mut ViewRect) -> tresult {
+        kResultOk
+    }
+
+    unsafe fn on_focus(&self, _state: TBool) -> tresult {
+        kResultOk
+    }
+
+    unsafe fn set_frame(&self, _frame: *mut c_void) -> tresult {
+        kResultOk
+    }
+
+    unsafe fn can_resize(&self) -> tresult {
+        kResultFalse
+    }
+
+    unsafe fn check_size_constraint(&self, _rect: *mut ViewRect) -> tresult {
+        kResultFalse
+    }
+}
+
+// Offers the single class of the library.
+#[VST3(implements(IPluginFactory))]
+pub struct Factory {
+    class_id: IID,
+    name: String,
+    new_component: fn() -> Box<Component>,
+}
+
+impl IPluginFactory for Factory {
+    unsafe fn get_factory_info(&self, info: *mut PFactoryInfo) -> tresult {
+        if info.is_null() {
+            return kInvalidArgument;
+        }
+
+        let info = &mut *info;
+        copy_cstr(&mut info.vendor, VENDOR);
+        copy_cstr(&mut info.url, URL);
+        copy_cstr(&mut info.email, "");
+        info.flags = 0;
+
+        kResultOk
+    }
+
+    unsafe fn count_classes(&self) -> i32 {
+        1
+    }
+
+    unsafe fn get_class_info(&self, index: i32, info: *mut PClassInfo) -> tresult {
+        if index != 0 || info.is_null() {
+            return kInvalidArgument;
+        }
+
+        let info = &mut *info;
+        info.cid = self.class_id;
+        info.cardinality = ClassCardinality::kManyInstances as i32;
+        copy_cstr(&mut info.category, "Audio Module Class");
+        copy_cstr(&mut info.name, &self.name);
+
+        kResultOk
+    }
+
+    unsafe fn create_instance(
+        &self,
+        cid: *const IID,
+        iid: *const IID,
+        obj: *mut *mut c_void,
+    ) -> tresult {
+        if cid.is_null() || iid.is_null() || obj.is_null() || *cid != self.class_id {
+            return kInvalidArgument;
+        }
+
+        // Hand out the interface asked for. The reference held around the query is
+        // dropped again, which frees the component if the query failed.
+        let component = Box::into_raw((self.new_component)());
+        (*component).add_ref();
+        let result = (*component).query_interface(iid, obj);
+        (*component).release();
+
+        if result == kResultOk {
+            kResultOk
+        } else {
+            *obj = ptr::null_mut();
+            kNoInterface
+        }
+    }
+}
+
+/// The factory returned by `GetPluginFactory`; see `jimtel::vst3_main!`.
+pub fn factory<P: WrappedPlugin>() -> *mut c_void {
+    let info = P::new(HostCallback::default()).get_info();
+
+    let factory = Factory::allocate(class_id(info.unique_id), info.name, Component::new::<P>);
+
+    Box::into_raw(factory) as *mut c_void
+}
+
+// Derived from the VST2 unique_id, so the dev build gets its own class as well.
+fn class_id(unique_id: i32) -> IID {
+    let mut data = [0; 16];
+    data[..12].copy_from_slice(b"JimtelVST3\0\0");
+    data[12..].copy_from_slice(&unique_id.to_be_bytes());
+
+    IID { data }
+}
+
+// The channel pointers of the first bus, and how many of them the plugins take.
+unsafe fn bus_channels(buses: *mut AudioBusBuffers, buses_num: i32) -> (*mut *mut c_void, usize) {
+    if buses.is_null() || buses_num < 1 || (*buses).buffers.is_null() {
+        return (ptr::null_mut(), 0);
+    }
+
+    let bus = &*buses;
+    (
+        bus.buffers,
+        (bus.num_channels.max(0) as usize).min(CHANNELS_NUM),
+    )
+}
+
+// Writes a null-terminated UTF-16 string, cut to fit.
+fn copy_wstr(dest: &mut [TChar], text: &str) {
+    let len = dest.len() - 1;
+    let units = text.encode_utf16().take(len).chain(iter::once(0));
+
+    for (dest, unit) in dest.iter_mut().zip(units) {
+        *dest = unit as TChar;
+    }
+}
+
+// Reads a null-terminated UTF-16 string of at most 128 units, as in `String128`.
+unsafe fn read_wstr(string: *const TChar) -> String {
+    let units: Vec<u16> = (0..128)
+        .map(|i| *string.add(i) as u16)
+        .take_while(|&unit| unit != 0)
+        .collect();
+
+    String::from_utf16_lossy(&units)
+}
+
+/// Exports the plugin as VST3 next to its VST2 entry point, from the same library.
+#[macro_export]
+macro_rules! vst3_main {
+    ($plugin:ty) => {
+        #[no_mangle]
+        #[allow(non_snake_case)]
+        pub extern "system" fn GetPluginFactory() -> *mut std::os::raw::c_void {
+            $crate::vst3::factory::<$plugin>()
+        }
+
+        #[cfg(target_os = "linux")]
+        #[no_mangle]
+        #[allow(non_snake_case)]
+        pub extern "system" fn ModuleEntry(_: *mut std::os::raw::c_void) -> bool {
+            true
+        }
+
+        #[cfg(target_os = "linux")]
+        #[no_mangle]
+        #[allow(non_snake_case)]
+        pub extern "system" fn ModuleExit() -> bool {
+            true
+        }
+
+        #[cfg(target_os = "macos")]
+        #[no_mangle]
+        #[allow(non_snake_case)]
+        pub extern "system" fn bundleEntry(_: *mut std::os::raw::c_void) -> bool {
+            true
+        }
+
+        #[cfg(target_os = "macos")]
+        #[no_mangle]
+        #[allow(non_snake_case)]
+        pub extern "system" fn bundleExit() -> bool {
+            true
+        }
+
+        #[cfg(target_os = "windows")]
+        #[no_mangle]
+        #[allow(non_snake_case)]
+        pub extern "system" fn InitDll() -> bool {
+            true
+        }
+
+        #[cfg(target_os = "windows")]
+        #[no_mangle]
+        #[allow(non_snake_case)]
+        pub extern "system" fn ExitDll() -> bool {
+            true
+        }
+    };
+}
@@ -0,0 +1,93 @@
+#[cfg(any(feature = "vst3", feature = "clap"))]
+use std::os::raw::c_char;
+use std::sync::Arc;
+
+use vst::plugin::Plugin;
+#[cfg(any(feature = "vst3", feature = "clap"))]
+use vst::plugin::{HostCallback, PluginParameters};
+
+use crate::params::Params;
+
+/// A plugin that the format wrappers (`jimtel::vst3_main!`, `jimtel::clap_main!`,
+/// `jimtel::lv2_main!`) can export on top of its VST2 implementation.
+pub trait WrappedPlugin: Plugin + Send + 'static {
+    type Params: Params + Send + Sync + 'static;
+
+    fn params(&self) -> Arc<Self::Params>;
+
+    /// How many samples the output lags the input by. Read again after every
+    /// block, so a change is passed on to the host.
+    fn latency_samples(&self) -> u32 {
+        0
+    }
+}
+
+// `WrappedPlugin` without its associated type, so the wrappers need not be generic.
+#[cfg(any(feature = "vst3", feature = "clap", feature = "lv2"))]
+pub(crate) trait Instance: Send {
+    fn plugin(&mut self) -> &mut dyn Plugin;
+    fn latency_samples(&self) -> u32;
+}
+
+#[cfg(any(feature = "vst3", feature = "clap", feature = "lv2"))]
+impl<P: WrappedPlugin> Instance for P {
+    fn plugin(&mut self) -> &mut dyn Plugin {
+        self
+    }
+
+    fn latency_samples(&self) -> u32 {
+        WrappedPlugin::latency_samples(self)
+    }
+}
+
+/// A new instance of the plugin with its params, as the wrappers hold them.
+#[cfg(any(feature = "vst3", feature = "clap"))]
+pub(crate) struct Wrapped {
+    pub instance: Box<dyn Instance>,
+    pub params: Arc<dyn Params + Send + Sync>,
+    pub parameters: Arc<dyn PluginParameters>,
+    pub params_num: i32,
+}
+
+#[cfg(any(feature = "vst3", feature = "clap"))]
+impl Wrapped {
+    pub fn new<P: WrappedPlugin>() -> Self {
+        // No VST2 host: the plugins only use it to report latency, which the
+        // wrappers poll through `latency_samples` instead.
+        let mut plugin = P::new(HostCallback::default());
+        plugin.init();
+
+        let params = plugin.params();
+        let parameters = plugin.get_parameter_object();
+
+        Self {
+            instance: Box::new(plugin),
+            params,
+            parameters,
+            params_num: P::Params::num_params() as i32,
+        }
+    }
+}
+
+/// Writes `text` into the fixed-size string field `dest` of a VST3 or CLAP
+/// struct, null-terminated and cut to fit.
+#[cfg(any(feature = "vst3", feature = "clap"))]
+pub(crate) fn copy_cstr(dest: &mut [c_char], text: &str) {
+    let len = dest.len() - 1;
+    let bytes = text.bytes().take(len).chain(std::iter::once(0));
+
+    for (dest, byte) in dest.iter_mut().zip(bytes) {
+        *dest = byte as c_char;
+    }
+}
+
+/// The plugin name as an identifier for CLAP ids and LV2 URIs: "Jimtel Loudness
+/// Limiter (dev)" becomes "jimtel_loudness_limiter_dev".
+#[cfg(any(feature = "clap", feature = "lv2"))]
+pub(crate) fn slug(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
+}
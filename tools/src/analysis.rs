@@ -0,0 +1,130 @@
+use serde::Serialize;
+
+use jimtel::loudness::{power_to_lkfs, IntegratedLoudness, Loudness, LoudnessRange};
+
+use crate::wav::Audio;
+
+const MOMENTARY_SECONDS: f32 = 0.4;
+const SHORT_TERM_SECONDS: f32 = 3.0;
+
+/// Loudness measurements of a whole file, taken with the meters of the plugins.
+/// Silence reads as null, having no finite loudness or level.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Report {
+    pub integrated_lkfs: f32,
+    pub max_momentary_lkfs: f32,
+    pub max_short_term_lkfs: f32,
+    pub loudness_range_lu: f32,
+    pub sample_peak_dbfs: f32,
+
+    /// Short-term loudness at the end of each whole second.
+    pub short_term_lkfs_per_second: Vec<f32>,
+}
+
+pub fn analyze(audio: &Audio) -> Report {
+    let sample_rate_hz = audio.sample_rate_hz as f32;
+    let channels_num = audio.channels.len();
+
+    let mut momentary = Loudness::new(
+        sample_rate_hz,
+        channels_num,
+        (sample_rate_hz * MOMENTARY_SECONDS) as usize,
+        1,
+    );
+    let mut short_term = Loudness::new(
+        sample_rate_hz,
+        channels_num,
+        (sample_rate_hz * SHORT_TERM_SECONDS) as usize,
+        1,
+    );
+    let mut integrated_loudness = IntegratedLoudness::new(sample_rate_hz, channels_num);
+    let mut loudness_range = LoudnessRange::new(sample_rate_hz, channels_num);
+
+    let mut max_momentary_power = 0.0f32;
+    let mut max_short_term_power = 0.0f32;
+    let mut sample_peak = 0.0f32;
+    let mut short_term_lkfs_per_second = Vec::new();
+
+    let mut samples = vec![0.0; channels_num];
+    for index in 0..audio.samples_num() {
+        for (sample, channel) in samples.iter_mut().zip(&audio.channels) {
+            *sample = channel[index];
+            sample_peak = sample_peak.max(sample.abs());
+        }
+
+        let (momentary_power, _) = momentary.add_samples(&samples);
+        let (short_term_power, _) = short_term.add_samples(&samples);
+        integrated_loudness.add_samples(&samples);
+        loudness_range.add_samples(&samples);
+
+        max_momentary_power = max_momentary_power.max(momentary_power);
+        max_short_term_power = max_short_term_power.max(short_term_power);
+
+        if (index + 1) % audio.sample_rate_hz as usize == 0 {
+            short_term_lkfs_per_second.push(power_to_lkfs(short_term_power));
+        }
+    }
+
+    Report {
+        integrated_lkfs: power_to_lkfs(integrated_loudness.power()),
+        max_momentary_lkfs: power_to_lkfs(max_momentary_power),
+        max_short_term_lkfs: power_to_lkfs(max_short_term_power),
+        loudness_range_lu: loudness_range.range(),
+        sample_peak_dbfs: 20.0 * sample_peak.log10(),
+        short_term_lkfs_per_second,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jimtel::test_host::sine;
+
+    use super::analyze;
+    use crate::wav::Audio;
+
+    #[test]
+    fn steady_sine_reads_the_same_on_every_meter() {
+        let report = analyze(&Audio {
+            sample_rate_hz: 48000,
+            channels: sine(48000.0, -23.0, 10.0),
+        });
+
+        assert!(
+            (report.integrated_lkfs - (-23.0)).abs() < 0.1,
+            "{:?}",
+            report
+        );
+        assert!(
+            (report.max_momentary_lkfs - (-23.0)).abs() < 0.1,
+            "{:?}",
+            report
+        );
+        assert!(
+            (report.max_short_term_lkfs - (-23.0)).abs() < 0.1,
+            "{:?}",
+            report
+        );
+        assert!(report.loudness_range_lu < 0.1, "{:?}", report);
+        assert!(
+            (report.sample_peak_dbfs - (-23.0)).abs() < 0.01,
+            "{:?}",
+            report
+        );
+
+        assert_eq!(report.short_term_lkfs_per_second.len(), 10);
+        assert!((report.short_term_lkfs_per_second[9] - (-23.0)).abs() < 0.1);
+    }
+
+    #[test]
+    fn silence_is_reported_as_null() {
+        let report = analyze(&Audio {
+            sample_rate_hz: 48000,
+            channels: vec![vec![0.0; 48000]; 2],
+        });
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert!(json["integrated_lkfs"].is_null());
+        assert!(json["sample_peak_dbfs"].is_null());
+        assert_eq!(json["loudness_range_lu"], 0.0);
+    }
+}
@@ -0,0 +1,58 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::process;
+
+use jimtel_tools::{analysis, wav};
+
+const USAGE: &str = "\
+usage: jimtel-analyze [options] <input.wav>
+
+Measures the loudness of a WAV file with the meters of the plugins and prints a
+JSON report: integrated loudness, max momentary and short-term loudness, loudness
+range, sample peak and the short-term loudness of every second.
+
+options:
+  --output <file>   write the report to a file instead of printing it";
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let mut output_path = None;
+    let mut input_path = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--output" => output_path = Some(args.next().ok_or("--output needs a value")?),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg).into()),
+            _ if input_path.is_none() => input_path = Some(arg),
+            _ => return Err(USAGE.into()),
+        }
+    }
+
+    let input_path = input_path.ok_or(USAGE)?;
+    let input = wav::read(BufReader::new(File::open(&input_path)?))?;
+    let report = analysis::analyze(&input);
+
+    match output_path {
+        Some(output_path) => {
+            let mut writer = BufWriter::new(File::create(output_path)?);
+            serde_json::to_writer_pretty(&mut writer, &report)?;
+            writeln!(writer)?;
+        }
+
+        None => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
+
+    Ok(())
+}
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("jimtel-analyze: {}", err);
+        process::exit(1);
+    }
+}
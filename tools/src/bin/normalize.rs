@@ -0,0 +1,91 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::process;
+
+use jimtel::normalize::{self, Target};
+use jimtel_tools::wav;
+
+const USAGE: &str = "\
+usage: jimtel-normalize [options] <input.wav> <output.wav>
+
+Normalises a WAV file in two passes, like ffmpeg's loudnorm: the first measures
+its integrated loudness and true peak, the second applies the static gain reaching
+the target, through the true-peak limiter only if the gain would take the true
+peak over the ceiling. Prints what was measured and done as JSON, and writes the
+output as 32-bit float.
+
+options:
+  --target <LKFS>      integrated loudness to reach (default -23)
+  --true-peak <dBTP>   true-peak ceiling (default -1)";
+
+fn parse_number(option: &str, value: Option<String>) -> Result<f32, String> {
+    let value = value.ok_or_else(|| format!("{} needs a value", option))?;
+
+    value
+        .parse()
+        .ok()
+        .filter(|value: &f32| value.is_finite())
+        .ok_or_else(|| format!("{} {} is not a number", option, value))
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let mut target = Target {
+        integrated_lkfs: -23.0,
+        true_peak_ceiling_dbtp: -1.0,
+    };
+    let mut paths = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--target" => target.integrated_lkfs = parse_number("--target", args.next())?,
+            "--true-peak" => {
+                target.true_peak_ceiling_dbtp = parse_number("--true-peak", args.next())?
+            }
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg).into()),
+            _ => paths.push(arg),
+        }
+    }
+
+    let (input_path, output_path) = match paths.as_slice() {
+        [input, output] => (input, output),
+        _ => return Err(USAGE.into()),
+    };
+
+    let mut audio = wav::read(BufReader::new(File::open(input_path)?))?;
+    let sample_rate_hz = audio.sample_rate_hz as f32;
+
+    let measurement = normalize::measure(sample_rate_hz, &audio.channels);
+    let normalization =
+        normalize::normalize(sample_rate_hz, &mut audio.channels, &measurement, &target);
+    let output_measurement = normalize::measure(sample_rate_hz, &audio.channels);
+
+    wav::write(BufWriter::new(File::create(output_path)?), &audio)?;
+
+    // Non-finite values, the loudness and peak of silence, print as null.
+    let report = serde_json::json!({
+        "input_integrated_lkfs": measurement.integrated_lkfs,
+        "input_true_peak_dbtp": measurement.true_peak_dbtp,
+        "target_integrated_lkfs": target.integrated_lkfs,
+        "target_true_peak_dbtp": target.true_peak_ceiling_dbtp,
+        "gain_db": normalization.gain_db,
+        "limited": normalization.limited,
+        "output_integrated_lkfs": output_measurement.integrated_lkfs,
+        "output_true_peak_dbtp": output_measurement.true_peak_dbtp,
+    });
+    println!("{}", serde_json::to_string_pretty(&report)?);
+
+    Ok(())
+}
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("jimtel-normalize: {}", err);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,145 @@
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::process;
+
+use vst::plugin::{HostCallback, Plugin};
+
+use jimtel::params::Params;
+use jimtel::wrapped::WrappedPlugin;
+use jimtel_tools::{render, wav};
+use loudness_limiter::LoudnessLimiter;
+
+const USAGE: &str = "\
+usage: jimtel-render [options] <input.wav> <output.wav>
+
+Runs the loudness limiter over a WAV file and writes its output as 32-bit float.
+
+options:
+  --bank <file>          load params from a bank chunk saved by the plugin
+  --param <name=value>   set a param, e.g. loudness=-16 or delay=1.5s (repeatable,
+                         applied after --bank)
+  --block-size <n>       samples per process call (default 512)
+  --list-params          print the params with their values and exit";
+
+struct Args {
+    bank: Option<String>,
+    params: Vec<(String, String)>,
+    block_size: usize,
+    list_params: bool,
+    paths: Vec<String>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        bank: None,
+        params: Vec::new(),
+        block_size: 512,
+        list_params: false,
+        paths: Vec::new(),
+    };
+
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = |option: &str| {
+            iter.next()
+                .ok_or_else(|| format!("{} needs a value", option))
+        };
+
+        match arg.as_str() {
+            "--bank" => args.bank = Some(value("--bank")?),
+            "--param" => {
+                let param = value("--param")?;
+                let (name, value) = param
+                    .split_once('=')
+                    .ok_or_else(|| format!("--param {} is not name=value", param))?;
+                args.params
+                    .push((name.trim().to_string(), value.to_string()));
+            }
+            "--block-size" => {
+                let block_size = value("--block-size")?;
+                args.block_size = block_size
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&block_size| block_size > 0)
+                    .ok_or_else(|| {
+                        format!("--block-size {} is not a positive number", block_size)
+                    })?;
+            }
+            "--list-params" => args.list_params = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg)),
+            _ => args.paths.push(arg),
+        }
+    }
+
+    Ok(args)
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let args = parse_args()?;
+
+    let mut plugin = LoudnessLimiter::new(HostCallback::default());
+    plugin.init();
+    let params = plugin.params();
+    let params_num = <LoudnessLimiter as WrappedPlugin>::Params::num_params() as i32;
+
+    if let Some(bank) = &args.bank {
+        plugin
+            .get_parameter_object()
+            .load_bank_data(&fs::read(bank)?);
+    }
+
+    for (name, value) in &args.params {
+        let index = (0..params_num)
+            .find(|&index| params.get_name(index) == *name)
+            .ok_or_else(|| format!("unknown param {} (see --list-params)", name))?;
+
+        if !params.set_value_text(index, value) {
+            return Err(format!("invalid value {} for {}", value, name).into());
+        }
+    }
+
+    if args.list_params {
+        for index in 0..params_num {
+            println!(
+                "{} = {}",
+                params.get_name(index),
+                params.get_value_text(index)
+            );
+        }
+
+        return Ok(());
+    }
+
+    let (input_path, output_path) = match args.paths.as_slice() {
+        [input, output] => (input, output),
+        _ => return Err(USAGE.into()),
+    };
+
+    let input = wav::read(BufReader::new(File::open(input_path)?))?;
+    if input.channels.len() > jimtel::channels::CHANNELS_NUM {
+        return Err(format!(
+            "{} has {} channels, at most {} are supported",
+            input_path,
+            input.channels.len(),
+            jimtel::channels::CHANNELS_NUM,
+        )
+        .into());
+    }
+
+    let output = render::render(&mut plugin, &input, args.block_size);
+    wav::write(BufWriter::new(File::create(output_path)?), &output)?;
+
+    Ok(())
+}
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("jimtel-render: {}", err);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,3 @@
+pub mod analysis;
+pub mod render;
+pub mod wav;
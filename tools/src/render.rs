@@ -0,0 +1,99 @@
+use vst::host::HostBuffer;
+
+use jimtel::wrapped::WrappedPlugin;
+
+use crate::wav::Audio;
+
+/// Runs `plugin` over the whole of `input` in blocks of `block_size` samples, as a
+/// host would, and returns what it outputs. The params are taken as set now.
+///
+/// The output is compensated for the plugin's latency, as a host would: it is fed
+/// that much silence after the input, and that much is trimmed from the start, so
+/// the output lines up with the input and is as long.
+pub fn render<P: WrappedPlugin>(plugin: &mut P, input: &Audio, block_size: usize) -> Audio {
+    let block_size = block_size.max(1);
+    let channels_num = input.channels.len();
+
+    plugin.set_sample_rate(input.sample_rate_hz as f32);
+    plugin.set_block_size(block_size as i64);
+    plugin.resume();
+
+    let latency_samples = plugin.latency_samples() as usize;
+    let samples_num = input.samples_num() + latency_samples;
+    let padded_input: Vec<Vec<f32>> = input
+        .channels
+        .iter()
+        .map(|channel| {
+            let mut channel = channel.clone();
+            channel.resize(samples_num, 0.0);
+            channel
+        })
+        .collect();
+
+    let mut output = vec![vec![0.0; samples_num]; channels_num];
+    let mut host_buffer = HostBuffer::new(channels_num, channels_num);
+
+    for start in (0..samples_num).step_by(block_size) {
+        let end = (start + block_size).min(samples_num);
+
+        let inputs: Vec<&[f32]> = padded_input
+            .iter()
+            .map(|channel| &channel[start..end])
+            .collect();
+        let mut outputs: Vec<&mut [f32]> = output
+            .iter_mut()
+            .map(|channel| &mut channel[start..end])
+            .collect();
+
+        plugin.process(&mut host_buffer.bind(&inputs, &mut outputs));
+    }
+
+    plugin.suspend();
+
+    for channel in &mut output {
+        channel.drain(..latency_samples);
+    }
+
+    Audio {
+        sample_rate_hz: input.sample_rate_hz,
+        channels: output,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vst::plugin::{HostCallback, Plugin};
+
+    use super::render;
+    use crate::wav::Audio;
+    use jimtel::params::Params;
+    use jimtel::test_host::sine;
+    use jimtel::wrapped::WrappedPlugin;
+    use loudness_limiter::LoudnessLimiter;
+
+    #[test]
+    fn delayed_render_lines_up_with_the_input() {
+        // A 3 s sine quiet enough to pass unchanged once the limiter has settled.
+        let input = Audio {
+            sample_rate_hz: 48000,
+            channels: sine(48000.0, -40.0, 3.0),
+        };
+
+        let mut plugin = LoudnessLimiter::new(HostCallback::default());
+        plugin.init();
+        let params = plugin.params();
+        let delay_index = (0..<LoudnessLimiter as WrappedPlugin>::Params::num_params() as i32)
+            .find(|&index| params.get_name(index) == "delay")
+            .unwrap();
+        assert!(params.set_value_text(delay_index, "10"));
+
+        let output = render(&mut plugin, &input, 512);
+        assert_eq!(plugin.latency_samples(), 480);
+
+        assert_eq!(output.sample_rate_hz, input.sample_rate_hz);
+        for (output, input) in output.channels.iter().zip(&input.channels) {
+            assert_eq!(output.len(), input.len());
+            assert_eq!(output[96000..], input[96000..]);
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use std::io::{Read, Seek, Write};
+
+use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
+
+/// Audio held as one buffer of samples in -1..1 per channel.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Audio {
+    pub sample_rate_hz: u32,
+    pub channels: Vec<Vec<f32>>,
+}
+
+impl Audio {
+    pub fn samples_num(&self) -> usize {
+        self.channels.first().map_or(0, |channel| channel.len())
+    }
+}
+
+/// Reads a PCM (8 to 32 bits) or 32-bit float WAV.
+pub fn read<R: Read>(reader: R) -> Result<Audio, hound::Error> {
+    let reader = WavReader::new(reader)?;
+    let spec = reader.spec();
+    let channels_num = spec.channels as usize;
+    if channels_num == 0 || spec.sample_rate == 0 {
+        return Err(hound::Error::FormatError("no channels or no sample rate"));
+    }
+
+    let samples = match spec.sample_format {
+        SampleFormat::Float => reader
+            .into_samples::<f32>()
+            .collect::<Result<Vec<_>, _>>()?,
+        SampleFormat::Int => {
+            let scale = 1.0 / (1u64 << (spec.bits_per_sample - 1)) as f32;
+
+            reader
+                .into_samples::<i32>()
+                .map(|sample| sample.map(|sample| sample as f32 * scale))
+                .collect::<Result<Vec<_>, _>>()?
+        }
+    };
+
+    let mut channels = vec![Vec::with_capacity(samples.len() / channels_num); channels_num];
+    for frame in samples.chunks(channels_num) {
+        for (channel, &sample) in channels.iter_mut().zip(frame) {
+            channel.push(sample);
+        }
+    }
+
+    Ok(Audio {
+        sample_rate_hz: spec.sample_rate,
+        channels,
+    })
+}
+
+/// Writes a 32-bit float WAV, which keeps the samples exactly as processed.
+pub fn write<W: Write + Seek>(writer: W, audio: &Audio) -> Result<(), hound::Error> {
+    let spec = WavSpec {
+        channels: audio.channels.len() as u16,
+        sample_rate: audio.sample_rate_hz,
+        bits_per_sample: 32,
+        sample_format: SampleFormat::Float,
+    };
+
+    let mut writer = WavWriter::new(writer, spec)?;
+    for index in 0..audio.samples_num() {
+        for channel in &audio.channels {
+            writer.write_sample(channel[index])?;
+        }
+    }
+
+    writer.finalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use hound::{SampleFormat, WavSpec, WavWriter};
+
+    use super::{read, write, Audio};
+
+    #[test]
+    fn pcm_is_read_into_minus_one_to_one() {
+        let mut data = Cursor::new(Vec::new());
+        let spec = WavSpec {
+            channels: 2,
+            sample_rate: 44100,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+
+        let mut writer = WavWriter::new(&mut data, spec).unwrap();
+        for sample in [i16::MIN, 0, 16384, -16384] {
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        data.set_position(0);
+        let audio = read(data).unwrap();
+
+        assert_eq!(audio.sample_rate_hz, 44100);
+        assert_eq!(audio.channels, vec![vec![-1.0, 0.5], vec![0.0, -0.5]]);
+    }
+
+    #[test]
+    fn float_is_written_and_read_back_exactly() {
+        let audio = Audio {
+            sample_rate_hz: 48000,
+            channels: vec![vec![0.1, -0.2, 1.5], vec![0.3, 0.0, -1.5]],
+        };
+
+        let mut data = Cursor::new(Vec::new());
+        write(&mut data, &audio).unwrap();
+        data.set_position(0);
+
+        assert_eq!(read(data).unwrap(), audio);
+    }
+}